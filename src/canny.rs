@@ -0,0 +1,132 @@
+use super::edge::EdgeMap;
+use super::filter::gaussian_blur;
+use super::gradient::{Gradient, GradientKernel};
+use super::image::GrayImage;
+
+/// Find thin, connected edges with the Canny edge detector: the image is
+/// blurred to suppress noise, edges are thinned out to the crest of the
+/// gradient, and then every pixel with a magnitude of at least
+/// `high_threshold` is kept, along with all pixels of at least
+/// `low_threshold`, that are connected to one of them
+pub fn canny_edges(
+	img: &GrayImage,
+	sigma: f32,
+	low_threshold: f32,
+	high_threshold: f32,
+) -> EdgeMap {
+	let blurred = gaussian_blur(img, sigma);
+	let magnitudes =
+		Gradient::new(&blurred, GradientKernel::Sobel).suppress_non_maxima();
+	hysteresis(
+		&magnitudes,
+		img.width(),
+		img.height(),
+		low_threshold,
+		high_threshold.max(low_threshold),
+	)
+}
+
+fn hysteresis(
+	magnitudes: &[f32],
+	width: usize,
+	height: usize,
+	low_threshold: f32,
+	high_threshold: f32,
+) -> EdgeMap {
+	let mut edges = EdgeMap::new(width, height);
+	let is_candidate = |m: f32| m > 0.0 && m >= low_threshold;
+	let mut stack: Vec<(usize, usize)> = Vec::new();
+	for y in 0..height {
+		for x in 0..width {
+			let m = magnitudes[x + y * width];
+			if is_candidate(m) && m >= high_threshold && !edges.get(x, y) {
+				edges.set(x, y, true);
+				stack.push((x, y));
+			}
+			// Follow all weak edge pixels, that are connected to this one
+			while let Some((cx, cy)) = stack.pop() {
+				for ny in cy.saturating_sub(1)..(cy + 2).min(height) {
+					for nx in cx.saturating_sub(1)..(cx + 2).min(width) {
+						if !edges.get(nx, ny) && is_candidate(magnitudes[nx + ny * width]) {
+							edges.set(nx, ny, true);
+							stack.push((nx, ny));
+						}
+					}
+				}
+			}
+		}
+	}
+	edges
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn edge_pixels(edges: &EdgeMap, width: usize, height: usize) -> usize {
+		(0..width * height)
+			.filter(|i| edges.get(i % width, i / width))
+			.count()
+	}
+
+	#[test]
+	fn hysteresis_follows_weak_edges() {
+		#[rustfmt::skip]
+		let magnitudes = [
+			0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+			0.0, 50.0, 10.0, 10.0, 0.0, 0.0,
+			0.0, 0.0, 0.0, 0.0, 10.0, 0.0,
+			0.0, 10.0, 0.0, 0.0, 0.0, 0.0,
+			0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+		];
+		let edges = hysteresis(&magnitudes, 6, 5, 5.0, 40.0);
+		assert!(edges.get(1, 1));
+		assert!(edges.get(2, 1));
+		assert!(edges.get(3, 1));
+		assert!(edges.get(4, 2));
+		// Weak, but not connected to a strong edge
+		assert!(!edges.get(1, 3));
+		assert_eq!(edge_pixels(&edges, 6, 5), 4);
+	}
+
+	#[test]
+	fn square_outline() {
+		// A bright square in the middle of a dark image gives a closed outline,
+		// that is one pixel wide
+		let img = GrayImage::from_fn(20, 20, |x, y| {
+			if (6..14).contains(&x) && (6..14).contains(&y) {
+				200.0
+			} else {
+				20.0
+			}
+		});
+		let edges = canny_edges(&img, 1.0, 10.0, 30.0);
+		let mut count = 0;
+		for y in 0..20 {
+			for x in 0..20 {
+				if !edges.get(x, y) {
+					continue;
+				}
+				count += 1;
+				// Every edge pixel lies on the border of the square
+				let on_vertical = (x == 6 || x == 13) && (6..=13).contains(&y);
+				let on_horizontal = (y == 6 || y == 13) && (6..=13).contains(&x);
+				assert!(on_vertical || on_horizontal, "{}, {}", x, y);
+				// No pixel has both a horizontal and a vertical neighbour inside
+				// the edge, which would make the line thicker than one pixel
+				let right = x < 19 && edges.get(x + 1, y);
+				let below = y < 19 && edges.get(x, y + 1);
+				let diagonal = x < 19 && y < 19 && edges.get(x + 1, y + 1);
+				assert!(!(right && below && diagonal), "{}, {}", x, y);
+			}
+		}
+		assert_eq!(count, 28);
+	}
+
+	#[test]
+	fn flat_image() {
+		let img = GrayImage::from_fn(10, 10, |_, _| 128.0);
+		let edges = canny_edges(&img, 1.4, 5.0, 10.0);
+		assert_eq!(edge_pixels(&edges, 10, 10), 0);
+	}
+}
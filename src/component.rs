@@ -0,0 +1,240 @@
+use super::coord::{Coord, Indexed, Point};
+use super::edge::EdgeMap;
+use super::quadtree::{QuadTree, Rectangle};
+use super::sort::sort_by_distance_2d;
+use super::stroke::Strokes;
+
+/// The smallest rectangle containing all pixels of a component. All sides are
+/// inclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bounds {
+	pub left: usize,
+	pub top: usize,
+	pub right: usize,
+	pub bottom: usize,
+}
+
+/// A group of edge pixels, that are connected to each other horizontally,
+/// vertically or diagonally
+#[derive(Clone, Debug, PartialEq)]
+pub struct Component {
+	points: Vec<Point>,
+	bounds: Bounds,
+}
+
+impl Component {
+	/// The number of pixels in the component
+	pub fn size(&self) -> usize {
+		self.points.len()
+	}
+
+	pub fn bounds(&self) -> Bounds {
+		self.bounds
+	}
+
+	/// The pixels of the component in row order
+	pub fn points(&self) -> &[Point] {
+		&self.points
+	}
+}
+
+/// Split the edges into 8-connected components. The components are ordered by
+/// their first pixel in row order.
+pub fn label_components(edges: &EdgeMap) -> Vec<Component> {
+	let (width, height) = (edges.width(), edges.height());
+	let mut visited = vec![false; width * height];
+	let mut components = Vec::new();
+	let mut stack = Vec::new();
+	for y in 0..height {
+		for x in 0..width {
+			if visited[x + y * width] || !edges.get(x, y) {
+				continue;
+			}
+			visited[x + y * width] = true;
+			stack.push((x, y));
+			let mut pixels = Vec::new();
+			let mut bounds = Bounds {
+				left: x,
+				top: y,
+				right: x,
+				bottom: y,
+			};
+			while let Some((x, y)) = stack.pop() {
+				pixels.push((x, y));
+				bounds.left = bounds.left.min(x);
+				bounds.right = bounds.right.max(x);
+				bounds.bottom = bounds.bottom.max(y);
+				for ny in y.saturating_sub(1)..(y + 2).min(height) {
+					for nx in x.saturating_sub(1)..(x + 2).min(width) {
+						if !visited[nx + ny * width] && edges.get(nx, ny) {
+							visited[nx + ny * width] = true;
+							stack.push((nx, ny));
+						}
+					}
+				}
+			}
+			pixels.sort_unstable_by_key(|&(x, y)| (y, x));
+			components.push(Component {
+				points: pixels
+					.into_iter()
+					.map(|(x, y)| Point::new(x as f32, y as f32))
+					.collect(),
+				bounds,
+			});
+		}
+	}
+	components
+}
+
+/// Remove the components with fewer than `min_size` pixels, which usually are
+/// noise
+pub fn drop_small_components(
+	components: Vec<Component>,
+	min_size: usize,
+) -> Vec<Component> {
+	components
+		.into_iter()
+		.filter(|c| c.size() >= min_size)
+		.collect()
+}
+
+/// Sort the points of each component on their own and chain the components by
+/// proximity, so that the path doesn't jump between unrelated features. Every
+/// component becomes one stroke. After a component is done, the next one is
+/// the one with the point closest to where the last one ended, and it is
+/// sorted starting from that point.
+pub fn sort_components<T: Coord>(
+	components: Vec<Vec<T>>,
+	w: f32,
+	h: f32,
+	initial_search_radius: f32,
+) -> Strokes<T> {
+	let mut components: Vec<Option<Vec<T>>> = components
+		.into_iter()
+		.map(|c| if c.is_empty() { None } else { Some(c) })
+		.collect();
+	let mut qt = QuadTree::new(Rectangle::new(w / 2.0, h / 2.0, w, h));
+	for (index, component) in components.iter().enumerate() {
+		for &point in component.iter().flatten() {
+			qt.insert(Indexed { point, index });
+		}
+	}
+
+	let mut strokes = Strokes::new();
+	let mut next = components.iter().position(|c| c.is_some()).map(|i| (i, 0));
+	while let Some((index, start)) = next {
+		let mut points = components[index].take().unwrap();
+		for &point in &points {
+			qt.remove(&Indexed { point, index });
+		}
+		points.swap(0, start);
+		let sorted = sort_by_distance_2d(points, w, h, initial_search_radius);
+		let end = *sorted.last().unwrap();
+		strokes.push(sorted);
+
+		next = match qt.nearest(end.x(), end.y()) {
+			Some(nearest) => {
+				let start = components[nearest.index]
+					.as_ref()
+					.unwrap()
+					.iter()
+					.position(|p| p.x() == nearest.x() && p.y() == nearest.y());
+				Some((nearest.index, start.unwrap()))
+			}
+			// Points sharing their position with another one are only
+			// stored once in the tree, so components might be left over
+			None => components.iter().position(|c| c.is_some()).map(|i| (i, 0)),
+		};
+	}
+	strokes
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn edge_map(rows: &[&str]) -> EdgeMap {
+		let mut edges = EdgeMap::new(rows[0].len(), rows.len());
+		for (y, row) in rows.iter().enumerate() {
+			for (x, c) in row.chars().enumerate() {
+				edges.set(x, y, c == '#');
+			}
+		}
+		edges
+	}
+
+	#[test]
+	fn labels() {
+		let edges = edge_map(&[
+			"##......#.",
+			"..#....#..",
+			"..#...#...",
+			"......#..#",
+			"...##.....",
+		]);
+		let components = label_components(&edges);
+		let sizes: Vec<usize> = components.iter().map(|c| c.size()).collect();
+		assert_eq!(sizes, vec![4, 4, 1, 2]);
+		assert_eq!(
+			components[0].bounds(),
+			Bounds {
+				left: 0,
+				top: 0,
+				right: 2,
+				bottom: 2
+			}
+		);
+		assert_eq!(
+			components[1].bounds(),
+			Bounds {
+				left: 6,
+				top: 0,
+				right: 8,
+				bottom: 3
+			}
+		);
+		assert_eq!(
+			components[0].points(),
+			&[
+				Point::new(0.0, 0.0),
+				Point::new(1.0, 0.0),
+				Point::new(2.0, 1.0),
+				Point::new(2.0, 2.0),
+			]
+		);
+
+		let components = drop_small_components(components, 2);
+		let sizes: Vec<usize> = components.iter().map(|c| c.size()).collect();
+		assert_eq!(sizes, vec![4, 4, 2]);
+	}
+
+	#[test]
+	fn components_stay_together() {
+		// Two parallel lines, each of which has to become one stroke
+		let top: Vec<Point> = (0..10).map(|x| Point::new(x as f32, 0.0)).collect();
+		let bottom: Vec<Point> =
+			(0..10).map(|x| Point::new(x as f32, 2.0)).collect();
+		let strokes = sort_components(vec![top, bottom], 10.0, 3.0, 2.0);
+		assert_eq!(strokes.len(), 2);
+		assert!(strokes.get(0).iter().all(|p| p.y() == 0.0));
+		assert!(strokes.get(1).iter().all(|p| p.y() == 2.0));
+		// The second line continues where the first one ended
+		assert_eq!(strokes.get(1)[0], Point::new(9.0, 2.0));
+		assert_eq!(strokes.get(1)[9], Point::new(0.0, 2.0));
+	}
+
+	#[test]
+	fn components_by_proximity() {
+		let line = |x: f32| -> Vec<Point> {
+			(0..4).map(|y| Point::new(x, y as f32)).collect()
+		};
+		let strokes = sort_components(
+			vec![line(0.0), line(20.0), line(2.0), line(10.0)],
+			24.0,
+			4.0,
+			2.0,
+		);
+		let order: Vec<f32> = strokes.iter().map(|s| s[0].x()).collect();
+		assert_eq!(order, vec![0.0, 2.0, 10.0, 20.0]);
+	}
+}
@@ -34,6 +34,24 @@ impl Coord for Point {
 	}
 }
 
+/// A point, that remembers which item it belongs to, e.g. its position in a
+/// list
+#[derive(Clone, Copy, Debug)]
+pub struct Indexed<T: Coord> {
+	pub point: T,
+	pub index: usize,
+}
+
+impl<T: Coord> Coord for Indexed<T> {
+	fn x(&self) -> f32 {
+		self.point.x()
+	}
+
+	fn y(&self) -> f32 {
+		self.point.y()
+	}
+}
+
 const POINT_BITDEPTH: u32 = 15;
 const MAX_POINT_VALUE: u32 = (1 << POINT_BITDEPTH) - 1;
 const POINT_OFFSET: f32 = MAX_POINT_VALUE as f32 / 2.0;
@@ -84,3 +102,9 @@ impl From<Point> for BitPoint {
 		Self::new(p.x(), p.y())
 	}
 }
+
+pub fn sq_dist<T: Coord>(a: &T, b: &T) -> f64 {
+	let dx = b.x() - a.x();
+	let dy = b.y() - a.y();
+	(dx * dx + dy * dy) as f64
+}
@@ -0,0 +1,333 @@
+use wasm_bindgen::prelude::*;
+
+use super::coord::{Coord, Point};
+use super::simplify::segment_dist;
+
+/// Recursion limit when flattening curves, which allows up to 2^16 lines per
+/// segment
+const MAX_FLATTEN_DEPTH: u32 = 16;
+
+/// How the knots of a Catmull-Rom spline are spaced. Centripetal splines
+/// don't form loops or cusps within a segment, and chordal ones hug the
+/// polyline more tightly around sharp turns.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parametrization {
+	/// Knots at equal intervals, like p5's `curveVertex`
+	Uniform = 0,
+	/// Knot intervals of the square root of the distance between the points
+	Centripetal = 1,
+	/// Knot intervals of the distance between the points
+	Chordal = 2,
+}
+
+impl Parametrization {
+	/// The exponent applied to the distances between the points
+	pub fn alpha(self) -> f32 {
+		match self {
+			Parametrization::Uniform => 0.0,
+			Parametrization::Centripetal => 0.5,
+			Parametrization::Chordal => 1.0,
+		}
+	}
+}
+
+/// One segment of a Catmull-Rom spline, which runs from `points[1]` to
+/// `points[2]`. The outer points guide its direction at both ends.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CatmullRom {
+	pub points: [Point; 4],
+	/// The parameter of each point along the spline
+	pub knots: [f32; 4],
+}
+
+impl CatmullRom {
+	/// The point at `t`, from 0 at the start of the segment to 1 at its end
+	#[cfg(test)]
+	pub fn at(&self, t: f32) -> Point {
+		let [p0, p1, p2, p3] = self.points;
+		let [t0, t1, t2, t3] = self.knots;
+		let t = t1 + (t2 - t1) * t;
+		// The pyramid of interpolations of Barry and Goldman
+		let blend =
+			|a: &Point, b: &Point, ta: f32, tb: f32| lerp(a, b, (t - ta) / (tb - ta));
+		let a1 = blend(&p0, &p1, t0, t1);
+		let a2 = blend(&p1, &p2, t1, t2);
+		let a3 = blend(&p2, &p3, t2, t3);
+		let b1 = blend(&a1, &a2, t0, t2);
+		let b2 = blend(&a2, &a3, t1, t3);
+		blend(&b1, &b2, t1, t2)
+	}
+
+	/// The Bézier curve, that follows the segment exactly
+	pub fn to_bezier(self) -> CubicBezier {
+		let [p0, p1, p2, p3] = self.points;
+		let [t0, t1, t2, t3] = self.knots;
+		// The derivatives at both ends, scaled to a parameter running from 0
+		// to 1 along the segment
+		let scale = (t2 - t1) / 3.0;
+		let tangent = |p0: &Point, p1: &Point, p2: &Point, t0, t1, t2| {
+			let along = |a: fn(&Point) -> f32| {
+				((a(p1) - a(p0)) / (t1 - t0) - (a(p2) - a(p0)) / (t2 - t0) +
+					(a(p2) - a(p1)) / (t2 - t1)) *
+					scale
+			};
+			Point::new(along(|p| p.x()), along(|p| p.y()))
+		};
+		let m1 = tangent(&p0, &p1, &p2, t0, t1, t2);
+		let m2 = tangent(&p1, &p2, &p3, t1, t2, t3);
+		CubicBezier {
+			from: p1,
+			c1: Point::new(p1.x() + m1.x(), p1.y() + m1.y()),
+			c2: Point::new(p2.x() - m2.x(), p2.y() - m2.y()),
+			to: p2,
+		}
+	}
+}
+
+/// Split a Catmull-Rom spline through the points into segments. Like p5's
+/// `curveVertex`, the first and the last point only guide the direction of
+/// the curve, which runs from the second to the second last point. Fewer than
+/// four points don't make a curve.
+pub fn catmull_rom<T: Coord>(
+	points: &[T],
+	parametrization: Parametrization,
+) -> Vec<CatmullRom> {
+	let alpha = parametrization.alpha();
+	points
+		.windows(4)
+		.map(|w| {
+			let points = [w[0], w[1], w[2], w[3]].map(to_point);
+			let mut knots = [0.0; 4];
+			for i in 1..4 {
+				let distance = dist(&points[i - 1], &points[i]);
+				// Points in the same place would make an interval of zero
+				knots[i] = knots[i - 1] + distance.powf(alpha).max(1e-4);
+			}
+			CatmullRom { points, knots }
+		})
+		.collect()
+}
+
+/// A cubic Bézier curve from `from` to `to`, with the control points `c1` and
+/// `c2`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CubicBezier {
+	pub from: Point,
+	pub c1: Point,
+	pub c2: Point,
+	pub to: Point,
+}
+
+impl CubicBezier {
+	/// Split the curve in two at `t`
+	pub fn split(&self, t: f32) -> (CubicBezier, CubicBezier) {
+		let a = lerp(&self.from, &self.c1, t);
+		let b = lerp(&self.c1, &self.c2, t);
+		let c = lerp(&self.c2, &self.to, t);
+		let ab = lerp(&a, &b, t);
+		let bc = lerp(&b, &c, t);
+		let middle = lerp(&ab, &bc, t);
+		(
+			CubicBezier {
+				from: self.from,
+				c1: a,
+				c2: ab,
+				to: middle,
+			},
+			CubicBezier {
+				from: middle,
+				c1: bc,
+				c2: c,
+				to: self.to,
+			},
+		)
+	}
+
+	/// Approximate the curve with lines, that are never farther than
+	/// `tolerance` from it. The start of the curve is left out, so that the
+	/// points of consecutive segments can be joined.
+	pub fn flatten(&self, tolerance: f32, polyline: &mut Vec<Point>) {
+		self.flatten_to_depth(tolerance, polyline, 0);
+	}
+
+	fn flatten_to_depth(
+		&self,
+		tolerance: f32,
+		polyline: &mut Vec<Point>,
+		depth: u32,
+	) {
+		// The curve stays within the hull of its control points, so it is
+		// close enough to the chord if they are
+		let flat = segment_dist(&self.c1, &self.from, &self.to) <= tolerance &&
+			segment_dist(&self.c2, &self.from, &self.to) <= tolerance;
+		if flat || depth >= MAX_FLATTEN_DEPTH {
+			polyline.push(self.to);
+		} else {
+			let (first, second) = self.split(0.5);
+			first.flatten_to_depth(tolerance, polyline, depth + 1);
+			second.flatten_to_depth(tolerance, polyline, depth + 1);
+		}
+	}
+}
+
+/// Convert a Catmull-Rom spline through the points into Bézier segments,
+/// which run from the second to the second last point
+pub fn catmull_rom_to_bezier<T: Coord>(
+	points: &[T],
+	parametrization: Parametrization,
+) -> Vec<CubicBezier> {
+	catmull_rom(points, parametrization)
+		.into_iter()
+		.map(CatmullRom::to_bezier)
+		.collect()
+}
+
+/// Trace the Catmull-Rom spline through the points with a polyline, that is
+/// never farther than `tolerance` from it
+pub fn sample_curve<T: Coord>(
+	points: &[T],
+	parametrization: Parametrization,
+	tolerance: f32,
+) -> Vec<Point> {
+	let segments = catmull_rom(points, parametrization);
+	let mut polyline = Vec::new();
+	if let Some(first) = segments.first() {
+		polyline.push(first.points[1]);
+	}
+	for segment in segments {
+		segment.to_bezier().flatten(tolerance, &mut polyline);
+	}
+	polyline
+}
+
+fn to_point<T: Coord>(p: T) -> Point {
+	Point::new(p.x(), p.y())
+}
+
+fn lerp(a: &Point, b: &Point, t: f32) -> Point {
+	Point::new(a.x() + (b.x() - a.x()) * t, a.y() + (b.y() - a.y()) * t)
+}
+
+fn dist(a: &Point, b: &Point) -> f32 {
+	(a.x() - b.x()).hypot(a.y() - b.y())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const P: fn(x: f32, y: f32) -> Point = Point::new;
+
+	#[test]
+	fn straight_line() {
+		let points = [P(0.0, 0.0), P(6.0, 0.0), P(12.0, 0.0), P(18.0, 0.0)];
+		let segments = catmull_rom_to_bezier(&points, Parametrization::Uniform);
+		assert_eq!(
+			segments,
+			vec![CubicBezier {
+				from: P(6.0, 0.0),
+				c1: P(8.0, 0.0),
+				c2: P(10.0, 0.0),
+				to: P(12.0, 0.0),
+			}]
+		);
+		assert!(
+			catmull_rom_to_bezier(&points[..3], Parametrization::Uniform).is_empty()
+		);
+	}
+
+	#[test]
+	fn segments_join_smoothly() {
+		let points = [
+			P(0.0, 0.0),
+			P(3.0, 6.0),
+			P(9.0, 3.0),
+			P(12.0, 9.0),
+			P(6.0, 12.0),
+		];
+		let segments = catmull_rom_to_bezier(&points, Parametrization::Uniform);
+		assert_eq!(segments.len(), 2);
+		assert_eq!(segments[0].to, segments[1].from);
+		// The tangents at the joint point in the same direction
+		let joint = segments[0].to;
+		let incoming = (
+			joint.x() - segments[0].c2.x(),
+			joint.y() - segments[0].c2.y(),
+		);
+		let outgoing = (
+			segments[1].c1.x() - joint.x(),
+			segments[1].c1.y() - joint.y(),
+		);
+		assert_eq!(incoming, outgoing);
+	}
+
+	fn curve() -> [Point; 6] {
+		[
+			P(0.0, 0.0),
+			P(2.0, 8.0),
+			P(3.0, 9.0),
+			P(20.0, 10.0),
+			P(21.0, 0.0),
+			P(30.0, 4.0),
+		]
+	}
+
+	#[test]
+	fn parametrizations() {
+		for parametrization in [
+			Parametrization::Uniform,
+			Parametrization::Centripetal,
+			Parametrization::Chordal,
+		] {
+			let segments = catmull_rom(&curve(), parametrization);
+			assert_eq!(segments.len(), 3);
+			for segment in segments {
+				// The segments run through the inner points, and the Bézier
+				// curves follow them exactly
+				assert_eq!(segment.at(0.0), segment.points[1]);
+				assert_eq!(segment.at(1.0), segment.points[2]);
+				let bezier = segment.to_bezier();
+				for i in 0..=10 {
+					let t = i as f32 / 10.0;
+					let expected = segment.at(t);
+					let (_, rest) = bezier.split(t);
+					assert!(dist(&rest.from, &expected) < 1e-3);
+				}
+			}
+		}
+		// Only uniform knots are evenly spaced
+		let uniform = catmull_rom(&curve(), Parametrization::Uniform);
+		assert_eq!(uniform[1].knots, [0.0, 1.0, 2.0, 3.0]);
+		let chordal = catmull_rom(&curve(), Parametrization::Chordal);
+		assert!(
+			(chordal[0].knots[2] - chordal[0].knots[1] - 2f32.sqrt()).abs() < 1e-5
+		);
+	}
+
+	#[test]
+	fn sampling() {
+		let tolerance = 0.1;
+		let polyline =
+			sample_curve(&curve(), Parametrization::Centripetal, tolerance);
+		assert_eq!(polyline.first(), Some(&P(2.0, 8.0)));
+		assert_eq!(polyline.last(), Some(&P(21.0, 0.0)));
+		assert!(polyline.len() > 10);
+		// Every point of the curve is close to the polyline
+		for segment in catmull_rom(&curve(), Parametrization::Centripetal) {
+			for i in 0..=100 {
+				let p = segment.at(i as f32 / 100.0);
+				let distance = polyline
+					.windows(2)
+					.map(|w| segment_dist(&p, &w[0], &w[1]))
+					.fold(f32::MAX, f32::min);
+				assert!(distance <= tolerance);
+			}
+		}
+		let coarse = sample_curve(&curve(), Parametrization::Centripetal, 2.0);
+		assert!(coarse.len() < polyline.len());
+		assert!(
+			sample_curve(&curve()[..3], Parametrization::Uniform, 0.1).is_empty()
+		);
+	}
+}
@@ -0,0 +1,145 @@
+use super::coord::{sq_dist, Coord};
+use super::quadtree::{Circle, QuadTree, Rectangle};
+use super::random::Random;
+
+/// Keep an evenly spaced `percentage` of the items, e.g. every fourth one for
+/// a percentage of `0.25`
+pub fn drop_out<T: Copy>(list: &[T], percentage: f32) -> Vec<T> {
+	let percentage = percentage as f64;
+	list
+		.iter()
+		.enumerate()
+		.filter(|(i, _)| {
+			(*i as f64 * percentage).floor() != ((i + 1) as f64 * percentage).floor()
+		})
+		.map(|(_, item)| *item)
+		.collect()
+}
+
+/// Keep each item with a probability of `percentage`. `random` has to return
+/// numbers between 0 and 1.
+pub fn drop_out_random<T: Copy, R: FnMut() -> f64>(
+	list: &[T],
+	percentage: f32,
+	mut random: R,
+) -> Vec<T> {
+	let percentage = percentage as f64;
+	list
+		.iter()
+		.filter(|_| random() <= percentage)
+		.copied()
+		.collect()
+}
+
+/// Keep each item with a probability of `percentage`, drawing the random
+/// numbers from a generator seeded with `seed`. The same seed always keeps the
+/// same items.
+pub fn drop_out_seeded<T: Copy>(
+	list: &[T],
+	percentage: f32,
+	seed: u64,
+) -> Vec<T> {
+	let mut random = Random::new(seed);
+	drop_out_random(list, percentage, || random.next_f64())
+}
+
+/// Keep the points such that no two kept points are closer than
+/// `min_distance`, like in a poisson disc sample. Unlike the percentage based
+/// dropouts, this thins out dense areas, while isolated points in sparse areas
+/// are kept. The points are visited in a random order, seeded with `seed`, and
+/// each one is kept if no point kept so far is too close. The kept points stay
+/// in their original order.
+pub fn drop_out_spaced<T: Coord>(
+	points: &[T],
+	min_distance: f32,
+	seed: u64,
+) -> Vec<T> {
+	let mut order: Vec<usize> = (0..points.len()).collect();
+	let mut random = Random::new(seed);
+	for i in (1..order.len()).rev() {
+		let j = (random.next_f64() * (i + 1) as f64) as usize;
+		order.swap(i, j);
+	}
+
+	let min_sq_dist = (min_distance * min_distance) as f64;
+	let mut kept = vec![false; points.len()];
+	let mut qt = QuadTree::new(Rectangle::around(points));
+	for i in order {
+		let p = points[i];
+		let range = Circle::new(p.x(), p.y(), min_distance as f64);
+		let too_close = qt
+			.query(&range)
+			.iter()
+			.any(|other| sq_dist(&p, other) < min_sq_dist);
+		if !too_close && qt.insert(p) {
+			kept[i] = true;
+		}
+	}
+	points
+		.iter()
+		.zip(kept)
+		.filter(|(_, kept)| *kept)
+		.map(|(p, _)| *p)
+		.collect()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::coord::Point;
+
+	#[test]
+	fn sequential() {
+		let list: Vec<u32> = (0..12).collect();
+		assert_eq!(drop_out(&list, 1.0), list);
+		assert_eq!(drop_out(&list, 0.0), vec![]);
+		assert_eq!(drop_out(&list, 0.25), vec![3, 7, 11]);
+		assert_eq!(drop_out(&list, 0.5).len(), 6);
+	}
+
+	#[test]
+	fn random() {
+		let list: Vec<u32> = (0..6).collect();
+		let mut values = [0.1, 0.9, 0.5, 0.2, 0.7, 0.4].into_iter();
+		let kept = drop_out_random(&list, 0.5, || values.next().unwrap());
+		assert_eq!(kept, vec![0, 2, 3, 5]);
+	}
+
+	#[test]
+	fn seeded() {
+		let list: Vec<u32> = (0..1000).collect();
+		let kept = drop_out_seeded(&list, 0.3, 7);
+		assert_eq!(kept, drop_out_seeded(&list, 0.3, 7));
+		assert_ne!(kept, drop_out_seeded(&list, 0.3, 8));
+		assert!((250..350).contains(&kept.len()));
+	}
+
+	#[test]
+	fn spaced() {
+		// A dense block of points and a few isolated ones
+		let mut points: Vec<Point> = (0..400)
+			.map(|i| Point::new((i % 20) as f32, (i / 20) as f32))
+			.collect();
+		points.extend([
+			Point::new(40.0, 5.0),
+			Point::new(45.0, 30.0),
+			Point::new(30.0, 35.0),
+		]);
+		let min_distance = 3.0;
+		let kept = drop_out_spaced(&points, min_distance, 3);
+		for (i, a) in kept.iter().enumerate() {
+			for b in &kept[i + 1..] {
+				assert!(sq_dist(a, b) >= (min_distance * min_distance) as f64);
+			}
+		}
+		// Every dropped point has a kept point nearby, that replaces it
+		for p in &points {
+			assert!(kept
+				.iter()
+				.any(|k| sq_dist(p, k) < (min_distance * min_distance) as f64));
+		}
+		assert!(kept.len() < 100);
+		assert!(kept.ends_with(&points[400..]));
+		assert_eq!(kept, drop_out_spaced(&points, min_distance, 3));
+	}
+}
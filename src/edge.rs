@@ -0,0 +1,175 @@
+use wasm_bindgen::prelude::*;
+
+use super::coord::Point;
+use super::image::{GrayImage, RgbImage};
+
+const COLOR_BIT_DEPTH: u32 = 8;
+
+/// The method used to find the edges of an image
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeDetector {
+	/// Reduce the bit depth of the image and mark every pixel, that differs
+	/// from its neighbours
+	Quantize = 0,
+	/// Mark every pixel, whose Sobel gradient magnitude reaches a threshold
+	Sobel = 1,
+	/// Mark every pixel, whose Scharr gradient magnitude reaches a threshold
+	Scharr = 2,
+	/// Find thin, connected edges with the Canny edge detector
+	Canny = 3,
+	/// Mark every pixel, whose color differs from that of its top, left or
+	/// top left neighbour by a threshold. Unlike the other detectors, this
+	/// compares the red, green and blue channels, so edges between colors of
+	/// the same brightness are found too.
+	ColorDistance = 4,
+}
+
+/// A map of the pixels of an image, that lie on an edge
+#[derive(Clone, Debug, PartialEq)]
+pub struct EdgeMap {
+	width: usize,
+	height: usize,
+	data: Vec<bool>,
+}
+
+impl EdgeMap {
+	pub fn new(width: usize, height: usize) -> Self {
+		Self {
+			width,
+			height,
+			data: vec![false; width * height],
+		}
+	}
+
+	pub fn width(&self) -> usize {
+		self.width
+	}
+
+	pub fn height(&self) -> usize {
+		self.height
+	}
+
+	pub fn get(&self, x: usize, y: usize) -> bool {
+		self.data[x + y * self.width]
+	}
+
+	pub fn set(&mut self, x: usize, y: usize, is_edge: bool) {
+		self.data[x + y * self.width] = is_edge;
+	}
+
+	/// The positions of all edge pixels, row by row
+	pub fn points(&self) -> Vec<Point> {
+		let mut points = Vec::new();
+		for y in 0..self.height {
+			for x in 0..self.width {
+				if self.get(x, y) {
+					points.push(Point::new(x as f32, y as f32));
+				}
+			}
+		}
+		points
+	}
+}
+
+/// Reduce the number of distinct gray values to `2 ^ bit_depth` and mark
+/// every pixel, whose reduced value differs from that of its top, left or top
+/// left neighbour
+pub fn quantized_edges(img: &GrayImage, bit_depth: u32) -> EdgeMap {
+	let loss = 2f32.powi(COLOR_BIT_DEPTH as i32 - bit_depth as i32);
+	let quantize = |x: usize, y: usize| (img.get(x, y) / loss).floor();
+	let mut edges = EdgeMap::new(img.width(), img.height());
+	for y in 1..img.height() {
+		for x in 1..img.width() {
+			let g0 = quantize(x, y);
+			let gt = quantize(x, y - 1);
+			let gl = quantize(x - 1, y);
+			let gtl = quantize(x - 1, y - 1);
+			if g0 != gt || g0 != gl || g0 != gtl {
+				edges.set(x, y, true);
+			}
+		}
+	}
+	edges
+}
+
+/// Mark every pixel, whose color is at least `threshold` away from that of its
+/// top, left or top left neighbour. The distance is the euclidean distance of
+/// the colors in RGB space.
+pub fn color_edges(img: &RgbImage, threshold: f32) -> EdgeMap {
+	let sq_threshold = threshold * threshold;
+	let differs = |a: [f32; 3], b: [f32; 3]| {
+		let sq_dist: f32 = a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum();
+		sq_dist >= sq_threshold
+	};
+	let mut edges = EdgeMap::new(img.width(), img.height());
+	for y in 1..img.height() {
+		for x in 1..img.width() {
+			let c0 = img.get(x, y);
+			if differs(c0, img.get(x, y - 1)) ||
+				differs(c0, img.get(x - 1, y)) ||
+				differs(c0, img.get(x - 1, y - 1))
+			{
+				edges.set(x, y, true);
+			}
+		}
+	}
+	edges
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::coord::{BitPoint, Coord};
+
+	fn edge_pixels(edges: &EdgeMap) -> Vec<(usize, usize)> {
+		edges
+			.points()
+			.iter()
+			.map(|p| (p.x() as usize, p.y() as usize))
+			.collect()
+	}
+
+	#[test]
+	fn vertical_step() {
+		let img = GrayImage::from_fn(4, 3, |x, _| if x < 2 { 0.0 } else { 255.0 });
+		let edges = quantized_edges(&img, 3);
+		assert_eq!(edge_pixels(&edges), vec![(2, 1), (2, 2)]);
+	}
+
+	#[test]
+	fn bit_depth() {
+		// A difference of 20 is lost, when only 8 gray values are left, but
+		// not with 64 of them
+		let img =
+			GrayImage::from_fn(3, 3, |_, y| if y < 1 { 100.0 } else { 120.0 });
+		assert_eq!(edge_pixels(&quantized_edges(&img, 3)), vec![]);
+		assert_eq!(edge_pixels(&quantized_edges(&img, 6)), vec![(1, 1), (2, 1)]);
+	}
+
+	#[test]
+	fn bit_point_encoding() {
+		// The points have to match the ones created by `createBitPoint` in JS
+		let mut edges = EdgeMap::new(8, 8);
+		edges.set(5, 7, true);
+		let raw = BitPoint::from(edges.points()[0]).to_raw();
+		assert_eq!(raw, ((5 + 16383) << 15) | (7 + 16383));
+	}
+
+	#[test]
+	fn color_distance() {
+		// Red and green of about the same brightness, which vanish in gray
+		let img = RgbImage::from_fn(4, 3, |x, _| {
+			if x < 2 {
+				[150.0, 0.0, 0.0]
+			} else {
+				[0.0, 150.0, 0.0]
+			}
+		});
+		let edges = color_edges(&img, 16.0);
+		assert_eq!(edge_pixels(&edges), vec![(2, 1), (2, 2)]);
+		let gray = img.to_gray(crate::image::Grayscale::Average);
+		assert!(quantized_edges(&gray, 3).points().is_empty());
+		assert!(color_edges(&img, 220.0).points().is_empty());
+	}
+}
@@ -0,0 +1,349 @@
+use wasm_bindgen::prelude::*;
+
+use super::image::GrayImage;
+
+/// A single step of a `FilterChain`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+	BoxBlur(usize),
+	GaussianBlur(f32),
+	EqualizeHistogram,
+	StretchContrast(f32),
+	Gamma(f32),
+}
+
+impl Filter {
+	pub fn apply(&self, img: &GrayImage) -> GrayImage {
+		match *self {
+			Filter::BoxBlur(radius) => box_blur(img, radius),
+			Filter::GaussianBlur(sigma) => gaussian_blur(img, sigma),
+			Filter::EqualizeHistogram => equalize_histogram(img),
+			Filter::StretchContrast(clip) => stretch_contrast(img, clip),
+			Filter::Gamma(gamma) => adjust_gamma(img, gamma),
+		}
+	}
+}
+
+/// An ordered list of filters, that the grayscale image is run through before
+/// its edges are detected
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FilterChain {
+	filters: Vec<Filter>,
+}
+
+#[wasm_bindgen]
+impl FilterChain {
+	#[wasm_bindgen(constructor)]
+	pub fn new() -> FilterChain {
+		Self::default()
+	}
+
+	/// Average each pixel with its neighbours up to `radius` pixels away
+	pub fn box_blur(&mut self, radius: u32) {
+		self.filters.push(Filter::BoxBlur(radius as usize));
+	}
+
+	/// Blur with a gaussian kernel with the standard deviation `sigma`
+	pub fn gaussian_blur(&mut self, sigma: f32) {
+		self.filters.push(Filter::GaussianBlur(sigma));
+	}
+
+	/// Spread the gray values, so that they are used about equally often
+	pub fn equalize_histogram(&mut self) {
+		self.filters.push(Filter::EqualizeHistogram);
+	}
+
+	/// Stretch the gray values linearly to the full range. `clip` is the share
+	/// of pixels, from 0 to 0.5, that may be clipped at either end.
+	pub fn stretch_contrast(&mut self, clip: f32) {
+		self.filters.push(Filter::StretchContrast(clip));
+	}
+
+	/// Apply a gamma correction. Values above 1 brighten the mid-tones, values
+	/// below 1 darken them. Black and white stay as they are.
+	pub fn gamma(&mut self, gamma: f32) {
+		self.filters.push(Filter::Gamma(gamma));
+	}
+
+	pub fn clear(&mut self) {
+		self.filters.clear();
+	}
+
+	pub fn len(&self) -> usize {
+		self.filters.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.filters.is_empty()
+	}
+}
+
+impl FilterChain {
+	/// Run the image through all filters in order
+	pub fn apply(&self, img: GrayImage) -> GrayImage {
+		self
+			.filters
+			.iter()
+			.fold(img, |img, filter| filter.apply(&img))
+	}
+}
+
+/// Blur the image by averaging each pixel with all pixels up to `radius` away
+/// horizontally and vertically. Pixels outside of the image are treated like
+/// the closest pixel on its border.
+pub fn box_blur(img: &GrayImage, radius: usize) -> GrayImage {
+	if radius == 0 {
+		return img.clone();
+	}
+	let size = 2 * radius + 1;
+	let kernel = vec![1.0 / size as f32; size];
+	let horizontal = convolve(img, &kernel, 1, 0);
+	convolve(&horizontal, &kernel, 0, 1)
+}
+
+/// Blur the image with a gaussian kernel. The kernel is cut off at three
+/// standard deviations and pixels outside of the image are treated like the
+/// closest pixel on its border.
+pub fn gaussian_blur(img: &GrayImage, sigma: f32) -> GrayImage {
+	if sigma <= 0.0 {
+		return img.clone();
+	}
+	let radius = (sigma * 3.0).ceil() as isize;
+	let mut kernel: Vec<f32> = (-radius..=radius)
+		.map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+		.collect();
+	let sum: f32 = kernel.iter().sum();
+	for k in kernel.iter_mut() {
+		*k /= sum;
+	}
+	let horizontal = convolve(img, &kernel, 1, 0);
+	convolve(&horizontal, &kernel, 0, 1)
+}
+
+/// Remap the gray values, so that each one is used about equally often. This
+/// brings out details in images, that are mostly dark or mostly bright.
+pub fn equalize_histogram(img: &GrayImage) -> GrayImage {
+	let histogram = histogram(img);
+	let mut cdf = [0usize; 256];
+	let mut total = 0;
+	for (i, count) in histogram.iter().enumerate() {
+		total += count;
+		cdf[i] = total;
+	}
+	let cdf_min = cdf.iter().copied().find(|&c| c > 0).unwrap_or(0);
+	if total == cdf_min {
+		return img.clone();
+	}
+	let scale = 255.0 / (total - cdf_min) as f32;
+	map_pixels(img, |v| (cdf[bin(v)] - cdf_min) as f32 * scale)
+}
+
+/// Stretch the gray values linearly, so that they span the whole range from 0
+/// to 255. Up to a share of `clip` of the pixels on either end of the
+/// histogram are ignored when the range is determined, so that a few outliers
+/// don't prevent the stretch.
+pub fn stretch_contrast(img: &GrayImage, clip: f32) -> GrayImage {
+	let histogram = histogram(img);
+	let pixel_count = img.width() * img.height();
+	let clipped = (pixel_count as f32 * clip.clamp(0.0, 0.5)) as usize;
+	let low = first_bin_above(&histogram, 0..256, clipped);
+	let high = first_bin_above(&histogram, (0..256).rev(), clipped);
+	let (low, high) = match (low, high) {
+		(Some(low), Some(high)) if low < high => (low as f32, high as f32),
+		_ => return img.clone(),
+	};
+	map_pixels(img, |v| {
+		((v - low) * 255.0 / (high - low)).clamp(0.0, 255.0)
+	})
+}
+
+/// Apply a gamma correction, mapping the gray values to
+/// `255 * (v / 255) ^ (1 / gamma)`
+pub fn adjust_gamma(img: &GrayImage, gamma: f32) -> GrayImage {
+	if gamma <= 0.0 {
+		return img.clone();
+	}
+	let exponent = 1.0 / gamma;
+	map_pixels(img, |v| {
+		255.0 * (v.clamp(0.0, 255.0) / 255.0).powf(exponent)
+	})
+}
+
+fn bin(value: f32) -> usize {
+	value.round().clamp(0.0, 255.0) as usize
+}
+
+fn histogram(img: &GrayImage) -> [usize; 256] {
+	let mut histogram = [0; 256];
+	for y in 0..img.height() {
+		for x in 0..img.width() {
+			histogram[bin(img.get(x, y))] += 1;
+		}
+	}
+	histogram
+}
+
+/// The first of the bins, at which the running total of the histogram exceeds
+/// `count`
+fn first_bin_above<I: Iterator<Item = usize>>(
+	histogram: &[usize; 256],
+	bins: I,
+	count: usize,
+) -> Option<usize> {
+	let mut total = 0;
+	for i in bins {
+		total += histogram[i];
+		if total > count {
+			return Some(i);
+		}
+	}
+	None
+}
+
+fn map_pixels<F: Fn(f32) -> f32>(img: &GrayImage, f: F) -> GrayImage {
+	let mut out = GrayImage::new(img.width(), img.height());
+	for y in 0..img.height() {
+		for x in 0..img.width() {
+			out.set(x, y, f(img.get(x, y)));
+		}
+	}
+	out
+}
+
+/// Convolve the image with a one dimensional kernel, that is applied along
+/// the direction `(dx, dy)`
+fn convolve(
+	img: &GrayImage,
+	kernel: &[f32],
+	dx: isize,
+	dy: isize,
+) -> GrayImage {
+	let w = img.width() as isize;
+	let h = img.height() as isize;
+	let radius = (kernel.len() / 2) as isize;
+	let mut out = GrayImage::new(img.width(), img.height());
+	for y in 0..h {
+		for x in 0..w {
+			let mut value = 0.0;
+			for (i, k) in kernel.iter().enumerate() {
+				let offset = i as isize - radius;
+				let sx = (x + offset * dx).clamp(0, w - 1);
+				let sy = (y + offset * dy).clamp(0, h - 1);
+				value += k * img.get(sx as usize, sy as usize);
+			}
+			out.set(x as usize, y as usize, value);
+		}
+	}
+	out
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn gaussian_preserves_flat_areas() {
+		let img = GrayImage::from_fn(5, 5, |_, _| 100.0);
+		let blurred = gaussian_blur(&img, 1.0);
+		for y in 0..5 {
+			for x in 0..5 {
+				assert!((blurred.get(x, y) - 100.0).abs() < 1e-3);
+			}
+		}
+	}
+
+	#[test]
+	fn gaussian_spreads_peaks() {
+		let img =
+			GrayImage::from_fn(
+				9,
+				9,
+				|x, y| if x == 4 && y == 4 { 255.0 } else { 0.0 },
+			);
+		let blurred = gaussian_blur(&img, 1.0);
+		assert!(blurred.get(4, 4) < 255.0);
+		assert!(blurred.get(4, 4) > blurred.get(5, 4));
+		assert!(blurred.get(5, 4) > blurred.get(6, 4));
+		assert!((blurred.get(5, 4) - blurred.get(4, 3)).abs() < 1e-4);
+		let total: f32 = (0..81).map(|i| blurred.get(i % 9, i / 9)).sum();
+		assert!((total - 255.0).abs() < 1e-2);
+	}
+
+	#[test]
+	fn box_blur_averages() {
+		let img =
+			GrayImage::from_fn(
+				5,
+				5,
+				|x, y| if x == 2 && y == 2 { 90.0 } else { 0.0 },
+			);
+		let blurred = box_blur(&img, 1);
+		for y in 0..5 {
+			for x in 0..5 {
+				let near = (1..4).contains(&x) && (1..4).contains(&y);
+				let expected = if near { 10.0 } else { 0.0 };
+				assert!((blurred.get(x, y) - expected).abs() < 1e-4);
+			}
+		}
+		assert_eq!(box_blur(&img, 0), img);
+	}
+
+	#[test]
+	fn equalize() {
+		// A dark image with four gray values, each on a quarter of the pixels
+		let img = GrayImage::from_fn(4, 4, |x, _| (x * 10) as f32);
+		let equalized = equalize_histogram(&img);
+		let row: Vec<f32> = (0..4).map(|x| equalized.get(x, 0)).collect();
+		assert_eq!(row, vec![0.0, 85.0, 170.0, 255.0]);
+		let flat = GrayImage::from_fn(4, 4, |_, _| 30.0);
+		assert_eq!(equalize_histogram(&flat), flat);
+	}
+
+	#[test]
+	fn stretch() {
+		let img = GrayImage::from_fn(10, 10, |x, _| (100 + x * 10) as f32);
+		let stretched = stretch_contrast(&img, 0.0);
+		assert_eq!(stretched.get(0, 0), 0.0);
+		assert_eq!(stretched.get(9, 0), 255.0);
+		assert!((stretched.get(3, 0) - 85.0).abs() < 1e-3);
+
+		// A single bright outlier is clipped
+		let img = GrayImage::from_fn(10, 10, |x, y| {
+			if x == 0 && y == 0 {
+				255.0
+			} else {
+				(100 + x * 10) as f32
+			}
+		});
+		let stretched = stretch_contrast(&img, 0.05);
+		assert_eq!(stretched.get(9, 1), 255.0);
+		assert_eq!(stretched.get(0, 0), 255.0);
+	}
+
+	#[test]
+	fn gamma() {
+		let img = GrayImage::from_fn(3, 1, |x, _| [0.0, 63.75, 255.0][x]);
+		let corrected = adjust_gamma(&img, 2.0);
+		assert_eq!(corrected.get(0, 0), 0.0);
+		assert!((corrected.get(1, 0) - 127.5).abs() < 1e-3);
+		assert_eq!(corrected.get(2, 0), 255.0);
+	}
+
+	#[test]
+	fn chain_order() {
+		let img = GrayImage::from_fn(4, 4, |x, _| (100 + x * 20) as f32);
+		let mut chain = FilterChain::new();
+		assert_eq!(chain.apply(img.clone()), img);
+		chain.gamma(2.0);
+		chain.stretch_contrast(0.0);
+		let mut reversed = FilterChain::new();
+		reversed.stretch_contrast(0.0);
+		reversed.gamma(2.0);
+		let a = chain.apply(img.clone());
+		let b = reversed.apply(img.clone());
+		assert_eq!(a, stretch_contrast(&adjust_gamma(&img, 2.0), 0.0));
+		assert_eq!(b, adjust_gamma(&stretch_contrast(&img, 0.0), 2.0));
+		assert_ne!(a, b);
+	}
+}
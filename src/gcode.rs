@@ -0,0 +1,270 @@
+use std::fmt::Write;
+
+use wasm_bindgen::prelude::*;
+
+use super::coord::Coord;
+use super::plot::{PlotTransform, Units};
+use super::smooth::{smooth, Smoothing};
+use super::stroke::Strokes;
+
+/// How the pen is lifted and lowered
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PenControl {
+	/// Move the Z axis to the pen up or pen down height with `G0 Z`
+	ZAxis = 0,
+	/// Set a servo to the pen up or pen down position with `M3 S`
+	Servo = 1,
+}
+
+/// Settings for plotting strokes with a G-code driven pen plotter. All lengths
+/// are in `units`. The program is written for grbl, like most pen plotters
+/// run.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GcodeConfig {
+	pub units: Units,
+	pub paper_width: f32,
+	pub paper_height: f32,
+	/// Blank space left on every side of the paper
+	pub margin: f32,
+	/// Speed, in units per minute, at which lines are drawn
+	pub draw_feed_rate: f32,
+	/// Speed, in units per minute, at which the pen travels between lines.
+	/// With 0, travel moves use `G0` and run at the machine's top speed.
+	pub travel_feed_rate: f32,
+	pub pen_control: PenControl,
+	/// Z height or servo value that lifts the pen
+	pub pen_up: f32,
+	/// Z height or servo value that lowers the pen
+	pub pen_down: f32,
+	/// Seconds to wait after lifting or lowering the pen. The wait is written
+	/// as `G4 P`, which grbl reads in seconds. Marlin reads it in
+	/// milliseconds, so there the delay needs to be given in milliseconds.
+	pub pen_delay: f32,
+	/// Strokes are interrupted between points, that are at least this far
+	/// apart in the frame
+	pub max_distance: f32,
+	/// How the parts of the strokes are smoothed before they are plotted
+	pub smoothing: Smoothing,
+	/// Iterations or window radius of the smoothing
+	pub smoothing_strength: u32,
+}
+
+#[wasm_bindgen]
+impl GcodeConfig {
+	/// Settings for an A4 sheet in portrait orientation, in millimeters
+	#[wasm_bindgen(constructor)]
+	pub fn new() -> GcodeConfig {
+		Self {
+			units: Units::Millimeters,
+			paper_width: 210.0,
+			paper_height: 297.0,
+			margin: 10.0,
+			draw_feed_rate: 1500.0,
+			travel_feed_rate: 3000.0,
+			pen_control: PenControl::ZAxis,
+			pen_up: 5.0,
+			pen_down: 0.0,
+			pen_delay: 0.0,
+			max_distance: 80.0,
+			smoothing: Smoothing::None,
+			smoothing_strength: 2,
+		}
+	}
+}
+
+impl Default for GcodeConfig {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Turn the strokes of a `frame_width` x `frame_height` frame into a G-code
+/// program, that draws them on paper. The frame is scaled to fit within the
+/// margins. Each stroke is drawn in one go with the pen down, strokes of a
+/// single point become dots.
+pub fn to_gcode<T: Coord>(
+	strokes: &Strokes<T>,
+	frame_width: f32,
+	frame_height: f32,
+	config: &GcodeConfig,
+) -> String {
+	let transform = PlotTransform::fit(
+		frame_width,
+		frame_height,
+		config.paper_width,
+		config.paper_height,
+		config.margin,
+	);
+	let mut program = GcodeWriter {
+		gcode: String::new(),
+		config,
+		feed_rate: None,
+	};
+	let units = match config.units {
+		Units::Millimeters => "G21",
+		Units::Inches => "G20",
+	};
+	program.line(units);
+	program.line("G90");
+	program.pen(config.pen_up);
+	let strokes = smooth(
+		&strokes.split_at_jumps(config.max_distance),
+		config.smoothing,
+		config.smoothing_strength,
+	);
+	for stroke in strokes.iter() {
+		let mut points = stroke.iter().map(|p| transform.apply(p));
+		let (x, y) = points.next().unwrap();
+		program.travel(x, y);
+		program.pen(config.pen_down);
+		for (x, y) in points {
+			program.draw(x, y);
+		}
+		program.pen(config.pen_up);
+	}
+	program.travel(0.0, 0.0);
+	program.gcode
+}
+
+struct GcodeWriter<'a> {
+	gcode: String,
+	config: &'a GcodeConfig,
+	// The last feed rate set, which stays active until it is changed
+	feed_rate: Option<f32>,
+}
+
+impl GcodeWriter<'_> {
+	fn line(&mut self, line: &str) {
+		self.gcode.push_str(line);
+		self.gcode.push('\n');
+	}
+
+	fn pen(&mut self, value: f32) {
+		let command = match self.config.pen_control {
+			PenControl::ZAxis => format!("G0 Z{}", num(value)),
+			PenControl::Servo => format!("M3 S{}", num(value)),
+		};
+		self.line(&command);
+		if self.config.pen_delay > 0.0 {
+			self.line(&format!("G4 P{}", num(self.config.pen_delay)));
+		}
+	}
+
+	fn travel(&mut self, x: f32, y: f32) {
+		if self.config.travel_feed_rate > 0.0 {
+			self.move_to(x, y, self.config.travel_feed_rate);
+		} else {
+			self.line(&format!("G0 X{} Y{}", num(x), num(y)));
+		}
+	}
+
+	fn draw(&mut self, x: f32, y: f32) {
+		self.move_to(x, y, self.config.draw_feed_rate);
+	}
+
+	fn move_to(&mut self, x: f32, y: f32, feed_rate: f32) {
+		write!(self.gcode, "G1 X{} Y{}", num(x), num(y)).unwrap();
+		if self.feed_rate != Some(feed_rate) {
+			write!(self.gcode, " F{}", num(feed_rate)).unwrap();
+			self.feed_rate = Some(feed_rate);
+		}
+		self.gcode.push('\n');
+	}
+}
+
+/// Format a number with at most three decimals and without trailing zeros
+fn num(value: f32) -> String {
+	// Adding 0 turns -0 into 0
+	format!("{}", (value * 1000.0).round() / 1000.0 + 0.0)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::coord::Point;
+
+	const P: fn(x: f32, y: f32) -> Point = Point::new;
+
+	fn strokes() -> Strokes<Point> {
+		// A triangle, a jump and a single dot
+		Strokes::single(vec![
+			P(0.0, 0.0),
+			P(100.0, 0.0),
+			P(50.0, 50.0),
+			P(0.0, 0.0),
+			P(100.0, 100.0),
+		])
+	}
+
+	fn config() -> GcodeConfig {
+		GcodeConfig {
+			paper_width: 120.0,
+			paper_height: 120.0,
+			margin: 10.0,
+			max_distance: 120.0,
+			..GcodeConfig::new()
+		}
+	}
+
+	#[test]
+	fn z_axis() {
+		let gcode = to_gcode(&strokes(), 100.0, 100.0, &config());
+		let expected = [
+			"G21",
+			"G90",
+			"G0 Z5",
+			"G1 X10 Y110 F3000",
+			"G0 Z0",
+			"G1 X110 Y110 F1500",
+			"G1 X60 Y60",
+			"G1 X10 Y110",
+			"G0 Z5",
+			"G1 X110 Y10 F3000",
+			"G0 Z0",
+			"G0 Z5",
+			"G1 X0 Y0",
+		];
+		assert_eq!(gcode.lines().collect::<Vec<_>>(), expected);
+	}
+
+	#[test]
+	fn servo_in_inches() {
+		let config = GcodeConfig {
+			units: Units::Inches,
+			paper_width: 5.0,
+			paper_height: 4.0,
+			margin: 0.5,
+			draw_feed_rate: 40.0,
+			travel_feed_rate: 0.0,
+			pen_control: PenControl::Servo,
+			pen_up: 30.0,
+			pen_down: 90.0,
+			pen_delay: 0.25,
+			..config()
+		};
+		let strokes = Strokes::single(vec![P(0.0, 0.0), P(100.0, 0.0)]);
+		let gcode = to_gcode(&strokes, 100.0, 100.0, &config);
+		let expected = [
+			"G20",
+			"G90",
+			"M3 S30",
+			"G4 P0.25",
+			"G0 X1 Y3.5",
+			"M3 S90",
+			"G4 P0.25",
+			"G1 X4 Y3.5 F40",
+			"M3 S30",
+			"G4 P0.25",
+			"G0 X0 Y0",
+		];
+		assert_eq!(gcode.lines().collect::<Vec<_>>(), expected);
+	}
+
+	#[test]
+	fn empty() {
+		let gcode = to_gcode(&Strokes::<Point>::new(), 100.0, 100.0, &config());
+		assert_eq!(gcode, "G21\nG90\nG0 Z5\nG1 X0 Y0 F3000\n");
+	}
+}
@@ -0,0 +1,216 @@
+use super::edge::EdgeMap;
+use super::image::GrayImage;
+
+/// The convolution kernel used to approximate the derivatives of an image
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GradientKernel {
+	Sobel,
+	/// Like Sobel, but with better rotational symmetry, so diagonal edges are
+	/// about as strong as horizontal and vertical ones
+	Scharr,
+}
+
+impl GradientKernel {
+	/// Weights of the three rows (or columns) the differences are taken over
+	fn weights(&self) -> [f32; 3] {
+		match self {
+			Self::Sobel => [1.0, 2.0, 1.0],
+			Self::Scharr => [3.0, 10.0, 3.0],
+		}
+	}
+}
+
+/// The horizontal and vertical derivatives of an image, in gray values per
+/// pixel. The outermost pixels have no full neighbourhood, so their gradient
+/// is zero.
+pub struct Gradient {
+	width: usize,
+	height: usize,
+	gx: Vec<f32>,
+	gy: Vec<f32>,
+}
+
+impl Gradient {
+	pub fn new(img: &GrayImage, kernel: GradientKernel) -> Self {
+		let width = img.width();
+		let height = img.height();
+		let mut gx = vec![0.0; width * height];
+		let mut gy = vec![0.0; width * height];
+		let [w0, w1, w2] = kernel.weights();
+		// The differences span two pixels
+		let norm = 2.0 * (w0 + w1 + w2);
+		for y in 1..height.saturating_sub(1) {
+			for x in 1..width.saturating_sub(1) {
+				let dx =
+					|dy: usize| img.get(x + 1, y + dy - 1) - img.get(x - 1, y + dy - 1);
+				let dy =
+					|dx: usize| img.get(x + dx - 1, y + 1) - img.get(x + dx - 1, y - 1);
+				let i = x + y * width;
+				gx[i] = (w0 * dx(0) + w1 * dx(1) + w2 * dx(2)) / norm;
+				gy[i] = (w0 * dy(0) + w1 * dy(1) + w2 * dy(2)) / norm;
+			}
+		}
+		Self {
+			width,
+			height,
+			gx,
+			gy,
+		}
+	}
+
+	pub fn magnitudes(&self) -> Vec<f32> {
+		self
+			.gx
+			.iter()
+			.zip(self.gy.iter())
+			.map(|(gx, gy)| gx.hypot(*gy))
+			.collect()
+	}
+
+	/// The gradient magnitudes, with every pixel set to zero, that isn't a
+	/// local maximum along the direction of its gradient. This thins out wide
+	/// edges to a width of one pixel.
+	pub fn suppress_non_maxima(&self) -> Vec<f32> {
+		let magnitudes = self.magnitudes();
+		let mut thinned = vec![0.0; magnitudes.len()];
+		for y in 1..self.height.saturating_sub(1) {
+			for x in 1..self.width.saturating_sub(1) {
+				let i = x + y * self.width;
+				let m = magnitudes[i];
+				if m == 0.0 {
+					continue;
+				}
+				let (dx, dy) = direction(self.gx[i], self.gy[i]);
+				let before = magnitudes[offset(x, y, -dx, -dy, self.width)];
+				let after = magnitudes[offset(x, y, dx, dy, self.width)];
+				// On a plateau of equal magnitudes, only the first pixel is kept
+				if m > before && m >= after {
+					thinned[i] = m;
+				}
+			}
+		}
+		thinned
+	}
+}
+
+/// Round the direction of a gradient to the closest of the eight neighbours
+fn direction(gx: f32, gy: f32) -> (isize, isize) {
+	let angle = gy.atan2(gx).to_degrees();
+	// Directions pointing in opposite ways are equivalent
+	let angle = if angle < 0.0 { angle + 180.0 } else { angle };
+	if !(22.5..157.5).contains(&angle) {
+		(1, 0)
+	} else if angle < 67.5 {
+		(1, 1)
+	} else if angle < 112.5 {
+		(0, 1)
+	} else {
+		(-1, 1)
+	}
+}
+
+fn offset(x: usize, y: usize, dx: isize, dy: isize, width: usize) -> usize {
+	let x = (x as isize + dx) as usize;
+	let y = (y as isize + dy) as usize;
+	x + y * width
+}
+
+/// Mark every pixel, whose gradient magnitude is at least `threshold`. With
+/// `non_max_suppression`, only the pixels at the crest of an edge are marked.
+pub fn gradient_edges(
+	img: &GrayImage,
+	kernel: GradientKernel,
+	threshold: f32,
+	non_max_suppression: bool,
+) -> EdgeMap {
+	let gradient = Gradient::new(img, kernel);
+	let magnitudes = if non_max_suppression {
+		gradient.suppress_non_maxima()
+	} else {
+		gradient.magnitudes()
+	};
+	let mut edges = EdgeMap::new(img.width(), img.height());
+	for y in 0..img.height() {
+		for x in 0..img.width() {
+			let m = magnitudes[x + y * img.width()];
+			if m > 0.0 && m >= threshold {
+				edges.set(x, y, true);
+			}
+		}
+	}
+	edges
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// Render an edge map as rows of `#` and `.`, to compare it with a golden
+	/// image
+	fn render(edges: &EdgeMap, img: &GrayImage) -> Vec<String> {
+		let mut rows = vec![String::new(); img.height()];
+		for (y, row) in rows.iter_mut().enumerate() {
+			for x in 0..img.width() {
+				row.push(if edges.get(x, y) { '#' } else { '.' });
+			}
+		}
+		rows
+	}
+
+	fn vertical_step() -> GrayImage {
+		GrayImage::from_fn(8, 5, |x, _| if x < 4 { 0.0 } else { 200.0 })
+	}
+
+	#[test]
+	fn step_magnitude() {
+		let img = vertical_step();
+		for kernel in [GradientKernel::Sobel, GradientKernel::Scharr] {
+			let magnitudes = Gradient::new(&img, kernel).magnitudes();
+			assert_eq!(&magnitudes[18..22], &[0.0, 100.0, 100.0, 0.0]);
+		}
+	}
+
+	#[test]
+	fn step_edges() {
+		let img = vertical_step();
+		let edges = gradient_edges(&img, GradientKernel::Sobel, 50.0, false);
+		assert_eq!(
+			render(&edges, &img),
+			vec!["........", "...##...", "...##...", "...##...", "........"]
+		);
+		let edges = gradient_edges(&img, GradientKernel::Sobel, 50.0, true);
+		assert_eq!(
+			render(&edges, &img),
+			vec!["........", "...#....", "...#....", "...#....", "........"]
+		);
+	}
+
+	#[test]
+	fn diagonal_step() {
+		let img =
+			GrayImage::from_fn(7, 7, |x, y| if x + y < 7 { 0.0 } else { 255.0 });
+		let edges = gradient_edges(&img, GradientKernel::Scharr, 50.0, true);
+		// The two pixels on either side of the step are no neighbours along the
+		// diagonal gradient direction, so both are kept and form a staircase
+		assert_eq!(
+			render(&edges, &img),
+			vec![
+				".......", ".....#.", "....##.", "...##..", "..##...", ".##....",
+				".......",
+			]
+		);
+	}
+
+	#[test]
+	fn gradient_threshold() {
+		// A smooth ramp, that gets brighter by 10 per pixel
+		let img = GrayImage::from_fn(6, 4, |x, _| x as f32 * 10.0);
+		let edges = gradient_edges(&img, GradientKernel::Sobel, 20.0, false);
+		assert_eq!(render(&edges, &img), vec!["......"; 4]);
+		let edges = gradient_edges(&img, GradientKernel::Sobel, 10.0, false);
+		assert_eq!(
+			render(&edges, &img),
+			vec!["......", ".####.", ".####.", "......"]
+		);
+	}
+}
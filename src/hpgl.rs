@@ -0,0 +1,162 @@
+use std::fmt::Write;
+
+use wasm_bindgen::prelude::*;
+
+use super::coord::{Coord, Point};
+use super::plot::PlotTransform;
+use super::smooth::{smooth, Smoothing};
+use super::stroke::Strokes;
+
+/// Settings for plotting strokes with an HP-GL plotter. Lengths are in
+/// millimeters.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HpglConfig {
+	pub paper_width: f32,
+	pub paper_height: f32,
+	/// Blank space left on every side of the paper
+	pub margin: f32,
+	/// Number of plotter units per millimeter. HP plotters use 40.
+	pub units_per_mm: f32,
+	/// The pen selected with `SP`
+	pub pen: u32,
+	/// Strokes are interrupted between points, that are at least this far
+	/// apart in the frame
+	pub max_distance: f32,
+	/// How the parts of the strokes are smoothed before they are plotted
+	pub smoothing: Smoothing,
+	/// Iterations or window radius of the smoothing
+	pub smoothing_strength: u32,
+}
+
+#[wasm_bindgen]
+impl HpglConfig {
+	/// Settings for an A4 sheet in landscape orientation
+	#[wasm_bindgen(constructor)]
+	pub fn new() -> HpglConfig {
+		Self {
+			paper_width: 297.0,
+			paper_height: 210.0,
+			margin: 10.0,
+			units_per_mm: 40.0,
+			pen: 1,
+			max_distance: 80.0,
+			smoothing: Smoothing::None,
+			smoothing_strength: 2,
+		}
+	}
+}
+
+impl Default for HpglConfig {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Turn the strokes of a `frame_width` x `frame_height` frame into HP-GL
+/// commands, that draw them on paper. The frame is scaled to fit within the
+/// margins and converted to plotter units. Every stroke becomes one pen up
+/// move to its start, followed by one pen down move through the remaining
+/// points.
+pub fn to_hpgl<T: Coord>(
+	strokes: &Strokes<T>,
+	frame_width: f32,
+	frame_height: f32,
+	config: &HpglConfig,
+) -> String {
+	let transform = PlotTransform::fit(
+		frame_width,
+		frame_height,
+		config.paper_width,
+		config.paper_height,
+		config.margin,
+	);
+	let to_plotter_units = |p: &Point| {
+		let (x, y) = transform.apply(p);
+		let x = (x * config.units_per_mm).round() as i32;
+		let y = (y * config.units_per_mm).round() as i32;
+		(x, y)
+	};
+	let mut hpgl = String::new();
+	writeln!(hpgl, "IN;").unwrap();
+	writeln!(hpgl, "SP{};", config.pen).unwrap();
+	let strokes = smooth(
+		&strokes.split_at_jumps(config.max_distance),
+		config.smoothing,
+		config.smoothing_strength,
+	);
+	for stroke in strokes.iter() {
+		let (x, y) = to_plotter_units(&stroke[0]);
+		writeln!(hpgl, "PU{},{};", x, y).unwrap();
+		hpgl.push_str("PD");
+		for (i, p) in stroke[1..].iter().enumerate() {
+			let (x, y) = to_plotter_units(p);
+			let separator = if i == 0 { "" } else { "," };
+			write!(hpgl, "{}{},{}", separator, x, y).unwrap();
+		}
+		hpgl.push_str(";\n");
+	}
+	writeln!(hpgl, "PU;").unwrap();
+	writeln!(hpgl, "SP0;").unwrap();
+	hpgl
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::coord::Point;
+
+	const P: fn(x: f32, y: f32) -> Point = Point::new;
+
+	#[test]
+	fn commands() {
+		let strokes = Strokes::from_parts(
+			vec![P(0.0, 0.0), P(100.0, 0.0), P(50.0, 50.0), P(100.0, 100.0)],
+			&[0, 3],
+		);
+		let config = HpglConfig {
+			paper_width: 120.0,
+			paper_height: 120.0,
+			margin: 10.0,
+			pen: 2,
+			max_distance: 200.0,
+			..HpglConfig::new()
+		};
+		let hpgl = to_hpgl(&strokes, 100.0, 100.0, &config);
+		let expected = [
+			"IN;",
+			"SP2;",
+			"PU400,4400;",
+			"PD4400,4400,2400,2400;",
+			"PU4400,400;",
+			"PD;",
+			"PU;",
+			"SP0;",
+		];
+		assert_eq!(hpgl.lines().collect::<Vec<_>>(), expected);
+	}
+
+	#[test]
+	fn scaling() {
+		// A wide frame on a tall sheet is fit to the width and centered
+		// vertically
+		let strokes = Strokes::single(vec![P(0.0, 0.0), P(200.0, 100.0)]);
+		let config = HpglConfig {
+			paper_width: 100.0,
+			paper_height: 200.0,
+			margin: 0.0,
+			units_per_mm: 10.0,
+			max_distance: 500.0,
+			..HpglConfig::new()
+		};
+		let hpgl = to_hpgl(&strokes, 200.0, 100.0, &config);
+		assert!(hpgl.contains("PU0,1250;\nPD1000,750;\n"));
+		// Jumps lift the pen
+		let config = HpglConfig {
+			max_distance: 50.0,
+			..config
+		};
+		let hpgl = to_hpgl(&strokes, 200.0, 100.0, &config);
+		assert!(hpgl.contains("PU0,1250;\nPD;\nPU1000,750;\nPD;\n"));
+	}
+}
@@ -0,0 +1,215 @@
+use wasm_bindgen::prelude::*;
+
+/// The formula used to turn the colors of a pixel into a gray value
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Grayscale {
+	/// The mean of the red, green and blue channels
+	Average = 0,
+	/// The luma of ITU-R BT.601, used by SD video
+	Rec601 = 1,
+	/// The luma of ITU-R BT.709, used by HD video
+	Rec709 = 2,
+	Red = 3,
+	Green = 4,
+	Blue = 5,
+	/// The brightest of the three channels, like the value of HSV
+	Value = 6,
+}
+
+impl Grayscale {
+	pub fn apply(self, [r, g, b]: [f32; 3]) -> f32 {
+		match self {
+			Grayscale::Average => (r + g + b) / 3.0,
+			Grayscale::Rec601 => 0.299 * r + 0.587 * g + 0.114 * b,
+			Grayscale::Rec709 => 0.2126 * r + 0.7152 * g + 0.0722 * b,
+			Grayscale::Red => r,
+			Grayscale::Green => g,
+			Grayscale::Blue => b,
+			Grayscale::Value => r.max(g).max(b),
+		}
+	}
+}
+
+/// A grayscale image with values ranging from 0 to 255
+#[derive(Clone, Debug, PartialEq)]
+pub struct GrayImage {
+	width: usize,
+	height: usize,
+	data: Vec<f32>,
+}
+
+impl GrayImage {
+	pub fn new(width: usize, height: usize) -> Self {
+		Self {
+			width,
+			height,
+			data: vec![0.0; width * height],
+		}
+	}
+
+	#[cfg(test)]
+	pub fn from_fn<F: Fn(usize, usize) -> f32>(
+		width: usize,
+		height: usize,
+		f: F,
+	) -> Self {
+		let mut data = Vec::with_capacity(width * height);
+		for y in 0..height {
+			for x in 0..width {
+				data.push(f(x, y));
+			}
+		}
+		Self {
+			width,
+			height,
+			data,
+		}
+	}
+
+	/// Convert the pixels of an RGBA buffer, like the one of an `ImageData`
+	/// object, to grayscale, by averaging their color channels
+	pub fn from_rgba(rgba: &[u8], width: usize, height: usize) -> Self {
+		RgbImage::from_rgba(rgba, width, height).to_gray(Grayscale::Average)
+	}
+
+	pub fn width(&self) -> usize {
+		self.width
+	}
+
+	pub fn height(&self) -> usize {
+		self.height
+	}
+
+	pub fn get(&self, x: usize, y: usize) -> f32 {
+		self.data[x + y * self.width]
+	}
+
+	pub fn set(&mut self, x: usize, y: usize, value: f32) {
+		self.data[x + y * self.width] = value;
+	}
+}
+
+/// A color image with red, green and blue values ranging from 0 to 255
+#[derive(Clone, Debug, PartialEq)]
+pub struct RgbImage {
+	width: usize,
+	height: usize,
+	data: Vec<[f32; 3]>,
+}
+
+impl RgbImage {
+	#[cfg(test)]
+	pub fn from_fn<F: Fn(usize, usize) -> [f32; 3]>(
+		width: usize,
+		height: usize,
+		f: F,
+	) -> Self {
+		let mut data = Vec::with_capacity(width * height);
+		for y in 0..height {
+			for x in 0..width {
+				data.push(f(x, y));
+			}
+		}
+		Self {
+			width,
+			height,
+			data,
+		}
+	}
+
+	/// Read the pixels of an RGBA buffer, like the one of an `ImageData`
+	/// object. The alpha channel is ignored.
+	pub fn from_rgba(rgba: &[u8], width: usize, height: usize) -> Self {
+		assert!(
+			rgba.len() >= width * height * 4,
+			"The RGBA buffer holds {} bytes, but a {}x{} image needs {}",
+			rgba.len(),
+			width,
+			height,
+			width * height * 4
+		);
+		let data = rgba
+			.chunks_exact(4)
+			.take(width * height)
+			.map(|px| [px[0] as f32, px[1] as f32, px[2] as f32])
+			.collect();
+		Self {
+			width,
+			height,
+			data,
+		}
+	}
+
+	pub fn width(&self) -> usize {
+		self.width
+	}
+
+	pub fn height(&self) -> usize {
+		self.height
+	}
+
+	pub fn get(&self, x: usize, y: usize) -> [f32; 3] {
+		self.data[x + y * self.width]
+	}
+
+	pub fn to_gray(&self, grayscale: Grayscale) -> GrayImage {
+		GrayImage {
+			width: self.width,
+			height: self.height,
+			data: self.data.iter().map(|&px| grayscale.apply(px)).collect(),
+		}
+	}
+}
+
+impl From<&GrayImage> for RgbImage {
+	fn from(img: &GrayImage) -> Self {
+		Self {
+			width: img.width,
+			height: img.height,
+			data: img.data.iter().map(|&v| [v, v, v]).collect(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn from_rgba() {
+		let rgba = [0, 0, 0, 255, 30, 60, 90, 255, 255, 255, 255, 0];
+		let img = GrayImage::from_rgba(&rgba, 3, 1);
+		assert_eq!(img.get(0, 0), 0.0);
+		assert_eq!(img.get(1, 0), 60.0);
+		assert_eq!(img.get(2, 0), 255.0);
+	}
+
+	#[test]
+	#[should_panic]
+	fn from_rgba_too_short() {
+		GrayImage::from_rgba(&[0; 12], 2, 2);
+	}
+
+	#[test]
+	fn grayscale() {
+		let rgba = [200, 100, 50, 255];
+		let img = RgbImage::from_rgba(&rgba, 1, 1);
+		let gray = |grayscale| img.to_gray(grayscale).get(0, 0);
+		assert!((gray(Grayscale::Average) - 116.666_67).abs() < 1e-3);
+		assert!((gray(Grayscale::Rec601) - 124.2).abs() < 1e-3);
+		assert!((gray(Grayscale::Rec709) - 117.65).abs() < 1e-3);
+		assert_eq!(gray(Grayscale::Red), 200.0);
+		assert_eq!(gray(Grayscale::Green), 100.0);
+		assert_eq!(gray(Grayscale::Blue), 50.0);
+		assert_eq!(gray(Grayscale::Value), 200.0);
+	}
+
+	#[test]
+	fn gray_to_rgb() {
+		let img = GrayImage::from_fn(2, 1, |x, _| x as f32 * 100.0);
+		let rgb = RgbImage::from(&img);
+		assert_eq!(rgb.get(1, 0), [100.0; 3]);
+		assert_eq!(rgb.to_gray(Grayscale::Average), img);
+	}
+}
@@ -1,15 +1,57 @@
 extern crate cfg_if;
 extern crate wasm_bindgen;
 
+mod canny;
+mod component;
 mod coord;
+mod curve;
+mod dropout;
+mod edge;
+mod filter;
+mod gcode;
+mod gradient;
+mod hpgl;
+mod image;
+mod mst;
+mod pipes;
+mod plot;
+mod png;
 mod quadtree;
+mod random;
+mod raster;
+mod simplify;
+mod smooth;
+mod sort;
+mod stroke;
+mod style;
+mod svg;
+mod thin;
+mod transform;
+mod travel;
 mod utils;
+mod width;
 
 use cfg_if::cfg_if;
 use wasm_bindgen::prelude::*;
 
 use coord::{BitPoint, Coord, Point};
-use quadtree::{QuadTree, Rectangle};
+pub use curve::Parametrization;
+pub use edge::EdgeDetector;
+pub use filter::FilterChain;
+pub use gcode::{GcodeConfig, PenControl};
+pub use hpgl::HpglConfig;
+pub use image::Grayscale;
+use image::{GrayImage, RgbImage};
+pub use pipes::PipeRouting;
+pub use plot::Units;
+pub use simplify::SimplifyMethod;
+pub use smooth::Smoothing;
+use sort::IncrementalSort;
+pub use sort::SortMode;
+use stroke::Strokes;
+pub use style::{DrawStyle, DrawingMode};
+pub use transform::TransformConfig;
+pub use width::{StrengthMap, WidthSource};
 
 cfg_if! {
 	if #[cfg(feature = "wee_alloc")] {
@@ -18,57 +60,118 @@ cfg_if! {
 	}
 }
 
-fn sq_dist<T: Coord>(a: &T, b: &T) -> f64 {
-	let dx = b.x() - a.x();
-	let dy = b.y() - a.y();
-	(dx * dx + dy * dy) as f64
+/// Sorted points split into strokes. `offsets` holds the index into `points`
+/// at which each stroke starts.
+#[wasm_bindgen]
+pub struct SortedStrokes {
+	points: Box<[u32]>,
+	offsets: Box<[u32]>,
 }
 
-fn sort_by_distance_2d<T: Coord>(
-	points: Vec<T>,
-	w: f32,
-	h: f32,
-	initial_search_radius: f32,
-) -> Vec<T> {
-	if points.len() <= 2 {
-		return points.clone();
-	}
-	let mut qt = QuadTree::new(Rectangle::new(w / 2.0, h / 2.0, w, h));
-	for p in points.iter() {
-		qt.insert(*p);
-	}
-	let mut sorted: Vec<T> = Vec::with_capacity(points.len());
-	let mut point = points[0];
-	let num_points = qt.size();
-
-	while sorted.len() < (num_points - 1) {
-		qt.remove(&point);
-		let mut search_radius = initial_search_radius;
-		let found = loop {
-			let search_range =
-				Rectangle::new(point.x(), point.y(), search_radius, search_radius);
-			let r = qt.query(&search_range);
-			search_radius *= 2.0;
-			if r.len() > 0 {
-				break r;
-			}
-		};
-		let mut closest: T = point;
-		let mut closest_dist = f64::INFINITY;
-		for i in 0..found.len() {
-			let element = found[i];
-			let d = sq_dist(&point, &element);
-			if d < closest_dist {
-				closest_dist = d;
-				closest = element;
-			}
+#[wasm_bindgen]
+impl SortedStrokes {
+	pub fn points(&self) -> Box<[u32]> {
+		self.points.clone()
+	}
+
+	pub fn offsets(&self) -> Box<[u32]> {
+		self.offsets.clone()
+	}
+}
+
+impl From<Strokes<Point>> for SortedStrokes {
+	fn from(strokes: Strokes<Point>) -> Self {
+		let (points, offsets) = strokes.into_parts();
+		Self {
+			points: points
+				.into_iter()
+				.map(|point| BitPoint::from(point).to_raw())
+				.collect(),
+			offsets: offsets.into_iter().map(|offset| offset as u32).collect(),
 		}
-		sorted.push(point);
-		point = closest;
 	}
-	sorted.push(point);
+}
 
-	sorted
+/// Strokes reordered to shorten the pen up travel between them, along with the
+/// travel distance before and after
+#[wasm_bindgen]
+pub struct OptimizedStrokes {
+	points: Box<[u32]>,
+	offsets: Box<[u32]>,
+	pub travel_before: f64,
+	pub travel_after: f64,
+}
+
+#[wasm_bindgen]
+impl OptimizedStrokes {
+	pub fn points(&self) -> Box<[u32]> {
+		self.points.clone()
+	}
+
+	pub fn offsets(&self) -> Box<[u32]> {
+		self.offsets.clone()
+	}
+}
+
+/// Strokes with vertices removed, along with the number of removed vertices
+#[wasm_bindgen]
+pub struct SimplifiedStrokes {
+	points: Box<[u32]>,
+	offsets: Box<[u32]>,
+	pub removed: u32,
+}
+
+#[wasm_bindgen]
+impl SimplifiedStrokes {
+	pub fn points(&self) -> Box<[u32]> {
+		self.points.clone()
+	}
+
+	pub fn offsets(&self) -> Box<[u32]> {
+		self.offsets.clone()
+	}
+}
+
+/// Smoothed strokes, or curves traced with lines. Their points are no longer
+/// on the pixel grid, so `points` holds the x and y coordinate of each point
+/// in turn.
+#[wasm_bindgen]
+pub struct SmoothedStrokes {
+	points: Box<[f32]>,
+	offsets: Box<[u32]>,
+}
+
+#[wasm_bindgen]
+impl SmoothedStrokes {
+	pub fn points(&self) -> Box<[f32]> {
+		self.points.clone()
+	}
+
+	pub fn offsets(&self) -> Box<[u32]> {
+		self.offsets.clone()
+	}
+}
+
+impl From<Strokes<Point>> for SmoothedStrokes {
+	fn from(strokes: Strokes<Point>) -> Self {
+		let (points, offsets) = strokes.into_parts();
+		Self {
+			points: points.iter().flat_map(|p| [p.x(), p.y()]).collect(),
+			offsets: offsets.into_iter().map(|offset| offset as u32).collect(),
+		}
+	}
+}
+
+fn from_raw_points(points: &[u32]) -> Vec<Point> {
+	points
+		.iter()
+		.map(|bit_point| BitPoint::from_raw(*bit_point).into())
+		.collect()
+}
+
+fn strokes_from_raw(points: &[u32], offsets: &[u32]) -> Strokes<Point> {
+	let offsets: Vec<usize> = offsets.iter().map(|&o| o as usize).collect();
+	Strokes::from_parts(from_raw_points(points), &offsets)
 }
 
 #[wasm_bindgen]
@@ -77,13 +180,21 @@ pub fn sort(
 	w: f32,
 	h: f32,
 	initial_search_radius: f32,
+) -> Box<[u32]> {
+	sort_with_mode(points, w, h, initial_search_radius, SortMode::Greedy)
+}
+
+#[wasm_bindgen]
+pub fn sort_with_mode(
+	points: &[u32],
+	w: f32,
+	h: f32,
+	initial_search_radius: f32,
+	mode: SortMode,
 ) -> Box<[u32]> {
 	utils::set_panic_hook();
-	let points: Vec<Point> = points
-		.iter()
-		.map(|bit_point| BitPoint::from_raw(*bit_point).into())
-		.collect();
-	let sorted = sort_by_distance_2d(points, w, h, initial_search_radius);
+	let points = from_raw_points(points);
+	let sorted = sort::sort_points(points, w, h, initial_search_radius, mode);
 	let sorted: Box<[u32]> = sorted
 		.into_iter()
 		.map(|point| BitPoint::from(point).to_raw())
@@ -91,31 +202,524 @@ pub fn sort(
 	sorted
 }
 
+#[wasm_bindgen]
+pub fn sort_strokes(
+	points: &[u32],
+	w: f32,
+	h: f32,
+	initial_search_radius: f32,
+	mode: SortMode,
+) -> SortedStrokes {
+	utils::set_panic_hook();
+	let points = from_raw_points(points);
+	sort::sort_strokes(points, w, h, initial_search_radius, mode).into()
+}
+
+/// Draw sorted strokes, like the `points` and `offsets` of `SortedStrokes`, as
+/// an SVG document
+#[wasm_bindgen]
+pub fn to_svg(points: &[u32], offsets: &[u32], style: &DrawStyle) -> String {
+	utils::set_panic_hook();
+	svg::to_svg(&strokes_from_raw(points, offsets), style, None)
+}
+
+/// Like `to_svg`, with the width of the lines depending on the strength of the
+/// edges in the map
+#[wasm_bindgen]
+pub fn to_svg_with_strength(
+	points: &[u32],
+	offsets: &[u32],
+	style: &DrawStyle,
+	strength: &StrengthMap,
+) -> String {
+	utils::set_panic_hook();
+	svg::to_svg(&strokes_from_raw(points, offsets), style, Some(strength))
+}
+
+/// Draw sorted strokes, like the `points` and `offsets` of `SortedStrokes`, as
+/// an RGBA image of the style's size, like the `data` of an `ImageData` object
+#[wasm_bindgen]
+pub fn to_rgba(
+	points: &[u32],
+	offsets: &[u32],
+	style: &DrawStyle,
+) -> Box<[u8]> {
+	utils::set_panic_hook();
+	let strokes = strokes_from_raw(points, offsets);
+	raster::render(&strokes, style, None).to_rgba().into()
+}
+
+/// Like `to_rgba`, with the width of the lines depending on the strength of
+/// the edges in the map
+#[wasm_bindgen]
+pub fn to_rgba_with_strength(
+	points: &[u32],
+	offsets: &[u32],
+	style: &DrawStyle,
+	strength: &StrengthMap,
+) -> Box<[u8]> {
+	utils::set_panic_hook();
+	let strokes = strokes_from_raw(points, offsets);
+	raster::render(&strokes, style, Some(strength))
+		.to_rgba()
+		.into()
+}
+
+/// Draw sorted strokes, like the `points` and `offsets` of `SortedStrokes`, as
+/// a PNG image of the style's size
+#[wasm_bindgen]
+pub fn to_png(points: &[u32], offsets: &[u32], style: &DrawStyle) -> Box<[u8]> {
+	utils::set_panic_hook();
+	let strokes = strokes_from_raw(points, offsets);
+	raster::render(&strokes, style, None).to_png().into()
+}
+
+/// Like `to_png`, with the width of the lines depending on the strength of the
+/// edges in the map
+#[wasm_bindgen]
+pub fn to_png_with_strength(
+	points: &[u32],
+	offsets: &[u32],
+	style: &DrawStyle,
+	strength: &StrengthMap,
+) -> Box<[u8]> {
+	utils::set_panic_hook();
+	let strokes = strokes_from_raw(points, offsets);
+	raster::render(&strokes, style, Some(strength))
+		.to_png()
+		.into()
+}
+
+/// Turn sorted strokes, like the `points` and `offsets` of `SortedStrokes`, into
+/// a G-code program for a pen plotter. The `frame_width` x `frame_height`
+/// frame of the points is scaled to fit the paper.
+#[wasm_bindgen]
+pub fn to_gcode(
+	points: &[u32],
+	offsets: &[u32],
+	frame_width: f32,
+	frame_height: f32,
+	config: &GcodeConfig,
+) -> String {
+	utils::set_panic_hook();
+	let strokes = strokes_from_raw(points, offsets);
+	gcode::to_gcode(&strokes, frame_width, frame_height, config)
+}
+
+/// Turn sorted strokes, like the `points` and `offsets` of `SortedStrokes`, into
+/// HP-GL commands for a plotter. The `frame_width` x `frame_height` frame of
+/// the points is scaled to fit the paper.
+#[wasm_bindgen]
+pub fn to_hpgl(
+	points: &[u32],
+	offsets: &[u32],
+	frame_width: f32,
+	frame_height: f32,
+	config: &HpglConfig,
+) -> String {
+	utils::set_panic_hook();
+	let strokes = strokes_from_raw(points, offsets);
+	hpgl::to_hpgl(&strokes, frame_width, frame_height, config)
+}
+
+/// Split sorted strokes at jumps of `max_distance` or more, then reorder and
+/// reverse them to shorten the distance a plotter travels with the pen up
+#[wasm_bindgen]
+pub fn optimize_travel(
+	points: &[u32],
+	offsets: &[u32],
+	max_distance: f32,
+) -> OptimizedStrokes {
+	utils::set_panic_hook();
+	let strokes = strokes_from_raw(points, offsets).split_at_jumps(max_distance);
+	let (optimized, report) = travel::optimize_travel(&strokes);
+	let SortedStrokes { points, offsets } = optimized.into();
+	OptimizedStrokes {
+		points,
+		offsets,
+		travel_before: report.before,
+		travel_after: report.after,
+	}
+}
+
+/// Split sorted strokes at jumps of `max_distance` or more, then remove the
+/// vertices, that are not needed to keep each part within `tolerance` of the
+/// original
+#[wasm_bindgen]
+pub fn simplify_strokes(
+	points: &[u32],
+	offsets: &[u32],
+	max_distance: f32,
+	tolerance: f32,
+	method: SimplifyMethod,
+) -> SimplifiedStrokes {
+	utils::set_panic_hook();
+	let strokes = strokes_from_raw(points, offsets).split_at_jumps(max_distance);
+	let (simplified, removed) = simplify::simplify(&strokes, tolerance, method);
+	let SortedStrokes { points, offsets } = simplified.into();
+	SimplifiedStrokes {
+		points,
+		offsets,
+		removed: removed as u32,
+	}
+}
+
+/// Split sorted strokes at jumps of `max_distance` or more, then smooth every
+/// part. `strength` is the number of iterations, or the window radius of the
+/// moving average.
+#[wasm_bindgen]
+pub fn smooth_strokes(
+	points: &[u32],
+	offsets: &[u32],
+	max_distance: f32,
+	smoothing: Smoothing,
+	strength: u32,
+) -> SmoothedStrokes {
+	utils::set_panic_hook();
+	let strokes = strokes_from_raw(points, offsets).split_at_jumps(max_distance);
+	smooth::smooth(&strokes, smoothing, strength).into()
+}
+
+/// Split sorted strokes at jumps of `max_distance` or more, then trace a
+/// Catmull-Rom spline through every part with lines, that stay within
+/// `tolerance` of it. Like the curves drawn by the app, the first and last
+/// point of every part only guide the spline.
+#[wasm_bindgen]
+pub fn sample_curves(
+	points: &[u32],
+	offsets: &[u32],
+	max_distance: f32,
+	parametrization: Parametrization,
+	tolerance: f32,
+) -> SmoothedStrokes {
+	utils::set_panic_hook();
+	let strokes = strokes_from_raw(points, offsets).split_at_jumps(max_distance);
+	let mut sampled = Strokes::new();
+	for stroke in strokes.iter() {
+		sampled.push(curve::sample_curve(stroke, parametrization, tolerance));
+	}
+	sampled.into()
+}
+
+/// Find the edge points of an RGBA image, like the `data` of an `ImageData`
+/// object, by reducing its bit depth and comparing each pixel with its top,
+/// left and top left neighbour
+#[wasm_bindgen]
+pub fn extract_edge_points(
+	rgba: &[u8],
+	width: u32,
+	height: u32,
+	bit_depth: u32,
+) -> Box<[u32]> {
+	utils::set_panic_hook();
+	let img = GrayImage::from_rgba(rgba, width as usize, height as usize);
+	edge::quantized_edges(&img, bit_depth)
+		.points()
+		.into_iter()
+		.map(|point| BitPoint::from(point).to_raw())
+		.collect()
+}
+
+/// Find the edge points of an RGBA image with the edge detector selected in
+/// `config`, after running it through `filters`
+#[wasm_bindgen]
+pub fn extract_edges(
+	rgba: &[u8],
+	width: u32,
+	height: u32,
+	config: &TransformConfig,
+	filters: &FilterChain,
+) -> Box<[u32]> {
+	utils::set_panic_hook();
+	let img = RgbImage::from_rgba(rgba, width as usize, height as usize);
+	transform::detect_rgb_edges(&img, config, filters)
+		.points()
+		.into_iter()
+		.map(|point| BitPoint::from(point).to_raw())
+		.collect()
+}
+
+/// Find the groups of connected edge pixels of an RGBA image, after running it
+/// through `filters`. Every group is
+/// reported as five numbers: its size in pixels and the left, top, right and
+/// bottom of its bounding box, all inclusive.
+#[wasm_bindgen]
+pub fn edge_components(
+	rgba: &[u8],
+	width: u32,
+	height: u32,
+	config: &TransformConfig,
+	filters: &FilterChain,
+) -> Box<[u32]> {
+	utils::set_panic_hook();
+	let img = RgbImage::from_rgba(rgba, width as usize, height as usize);
+	let edges = transform::detect_rgb_edges(&img, config, filters);
+	component::label_components(&edges)
+		.iter()
+		.flat_map(|c| {
+			let b = c.bounds();
+			[c.size(), b.left, b.top, b.right, b.bottom].map(|v| v as u32)
+		})
+		.collect()
+}
+
+/// Keep a `percentage` of the points, either every nth one or, if `random` is
+/// set, randomly chosen ones. The same `seed` always keeps the same points.
+#[wasm_bindgen]
+pub fn drop_out_points(
+	points: &[u32],
+	percentage: f32,
+	random: bool,
+	seed: u64,
+) -> Box<[u32]> {
+	utils::set_panic_hook();
+	if random {
+		dropout::drop_out_seeded(points, percentage, seed).into_boxed_slice()
+	} else {
+		dropout::drop_out(points, percentage).into_boxed_slice()
+	}
+}
+
+/// Keep the points such that no two of them are closer than `min_distance`,
+/// thinning out dense areas, while keeping isolated points. The points are
+/// visited in a random order seeded with `seed`.
+#[wasm_bindgen]
+pub fn drop_out_spaced_points(
+	points: &[u32],
+	min_distance: f32,
+	seed: u64,
+) -> Box<[u32]> {
+	utils::set_panic_hook();
+	let points: Vec<BitPoint> =
+		points.iter().map(|&p| BitPoint::from_raw(p)).collect();
+	dropout::drop_out_spaced(&points, min_distance, seed)
+		.into_iter()
+		.map(|p| p.to_raw())
+		.collect()
+}
+
+/// Run the whole pipeline of turning a video frame into drawable strokes in
+/// one go: run the RGBA image through `filters`, detect its edges, drop some
+/// of the edge points, sort them and scale them to the target size. When the
+/// components are sorted on their own, every component is a stroke.
+#[wasm_bindgen]
+pub fn transform_image(
+	rgba: &[u8],
+	src_width: u32,
+	src_height: u32,
+	target_width: f32,
+	target_height: f32,
+	config: &TransformConfig,
+	filters: &FilterChain,
+) -> SortedStrokes {
+	utils::set_panic_hook();
+	let img = RgbImage::from_rgba(rgba, src_width as usize, src_height as usize);
+	transform::transform_image(&img, target_width, target_height, config, filters)
+		.into()
+}
+
+/// A resumable sort, that can be spread over multiple animation frames. Call
+/// `step` with the number of points to place in the current frame until it
+/// returns `true`, or `free` the state to abandon the frame.
+#[wasm_bindgen]
+pub struct SortState {
+	inner: IncrementalSort<Point>,
+}
+
+#[wasm_bindgen]
+impl SortState {
+	#[wasm_bindgen(constructor)]
+	pub fn new(
+		points: &[u32],
+		w: f32,
+		h: f32,
+		initial_search_radius: f32,
+		mode: SortMode,
+	) -> SortState {
+		utils::set_panic_hook();
+		let points = from_raw_points(points);
+		Self {
+			inner: IncrementalSort::new(points, w, h, initial_search_radius, mode),
+		}
+	}
+
+	/// Place up to `budget` more points, or take as many steps of similar cost
+	/// when sorting by minimum spanning tree. Returns `true` once all points
+	/// are sorted.
+	pub fn step(&mut self, budget: u32) -> bool {
+		self.inner.step(budget as usize)
+	}
+
+	pub fn is_done(&self) -> bool {
+		self.inner.is_done()
+	}
+
+	pub fn remaining(&self) -> u32 {
+		self.inner.remaining() as u32
+	}
+
+	/// The points sorted so far
+	pub fn sorted(&self) -> Box<[u32]> {
+		self
+			.inner
+			.sorted()
+			.map(|point| BitPoint::from(*point).to_raw())
+			.collect()
+	}
+}
+
+/// Sorts frame after frame with the same settings. The quadtree and the
+/// buffers are kept between calls, so a live video feed doesn't need to
+/// allocate them anew for every frame.
+///
+/// Besides sorting a JS array in place, points can be passed without any
+/// copies between JS and wasm: `input_buffer` reserves space for the points
+/// in wasm memory, JS writes them into a `Uint32Array` view of that space,
+/// `sort_input` sorts them and the result can be read through a view of
+/// `output_ptr` and `output_len`. Views over wasm memory are invalidated,
+/// when the memory grows, so they should be recreated after every call.
+#[wasm_bindgen]
+pub struct Sorter {
+	state: IncrementalSort<Point>,
+	input: Vec<u32>,
+	output: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl Sorter {
+	#[wasm_bindgen(constructor)]
+	pub fn new(
+		w: f32,
+		h: f32,
+		initial_search_radius: f32,
+		capacity: u32,
+		mode: SortMode,
+	) -> Sorter {
+		utils::set_panic_hook();
+		Self {
+			state: IncrementalSort::empty(
+				w,
+				h,
+				initial_search_radius,
+				mode,
+				capacity as usize,
+			),
+			input: Vec::new(),
+			output: Vec::new(),
+		}
+	}
+
+	/// Sort the points in place and return how many of them are valid.
+	/// Duplicates and points outside of the frame are dropped, so the sorted
+	/// list can be shorter than the input. Anything after it is left as is.
+	pub fn sort(&mut self, points: &mut [u32]) -> u32 {
+		self.state.restart(
+			points
+				.iter()
+				.map(|bit_point| Point::from(BitPoint::from_raw(*bit_point))),
+		);
+		self.state.finish();
+		let mut len = 0;
+		for (slot, point) in points.iter_mut().zip(self.state.sorted()) {
+			*slot = BitPoint::from(*point).to_raw();
+			len += 1;
+		}
+		len
+	}
+
+	/// Make room for `len` input points and return a pointer to them
+	pub fn input_buffer(&mut self, len: u32) -> *mut u32 {
+		self.input.resize(len as usize, 0);
+		self.input.as_mut_ptr()
+	}
+
+	/// Sort the points in the input buffer into the output buffer and return
+	/// the number of sorted points
+	pub fn sort_input(&mut self) -> u32 {
+		self.state.restart(
+			self
+				.input
+				.iter()
+				.map(|bit_point| Point::from(BitPoint::from_raw(*bit_point))),
+		);
+		self.state.finish();
+		self.output.clear();
+		self.output.extend(
+			self
+				.state
+				.sorted()
+				.map(|point| BitPoint::from(*point).to_raw()),
+		);
+		self.output.len() as u32
+	}
+
+	pub fn output_ptr(&self) -> *const u32 {
+		self.output.as_ptr()
+	}
+
+	pub fn output_len(&self) -> u32 {
+		self.output.len() as u32
+	}
+}
+
 #[cfg(test)]
 mod lib_test {
 	use super::*;
 
 	#[test]
-	fn basic() {
-		let sorted = sort_by_distance_2d(
-			vec![
-				Point::new(0.0, 0.0),
-				Point::new(3.0, 3.0),
-				Point::new(1.0, 1.0),
-				Point::new(4.0, 4.0),
-				Point::new(2.0, 2.0),
-			],
-			6.0,
-			6.0,
-			2.0,
-		);
-		let ordered = vec![
-			Point::new(0.0, 0.0),
-			Point::new(1.0, 1.0),
-			Point::new(2.0, 2.0),
-			Point::new(3.0, 3.0),
-			Point::new(4.0, 4.0),
-		];
-		assert_eq!(sorted, ordered);
+	fn sorter_in_place() {
+		let points: Vec<u32> = [
+			(1.0, 1.0),
+			(4.0, 4.0),
+			(2.0, 2.0),
+			(4.0, 4.0),
+			(5.0, 5.0),
+			(3.0, 3.0),
+		]
+		.iter()
+		.map(|&(x, y)| BitPoint::new(x, y).to_raw())
+		.collect();
+		let mut sorter = Sorter::new(6.0, 6.0, 2.0, 4, SortMode::Greedy);
+		for _ in 0..2 {
+			let mut buffer = points.clone();
+			let len = sorter.sort(&mut buffer) as usize;
+			assert_eq!(len, 5);
+			assert_eq!(&buffer[..len], &*sort(&points, 6.0, 6.0, 2.0));
+		}
+	}
+
+	#[test]
+	fn sorter_drops_points_outside_of_frame() {
+		let mut points: Vec<u32> = [(9.0, 9.0), (1.0, 1.0), (3.0, 3.0), (2.0, 2.0)]
+			.iter()
+			.map(|&(x, y)| BitPoint::new(x, y).to_raw())
+			.collect();
+		let mut sorter = Sorter::new(4.0, 4.0, 2.0, 4, SortMode::Greedy);
+		let len = sorter.sort(&mut points) as usize;
+		assert_eq!(len, 3);
+		let expected: Vec<u32> = [(1.0, 1.0), (2.0, 2.0), (3.0, 3.0)]
+			.iter()
+			.map(|&(x, y)| BitPoint::new(x, y).to_raw())
+			.collect();
+		assert_eq!(&points[..len], &expected[..]);
+	}
+
+	#[test]
+	fn sorter_shared_buffers() {
+		let points: Vec<u32> = (0..20)
+			.rev()
+			.map(|i| BitPoint::new(i as f32, (i % 3) as f32).to_raw())
+			.collect();
+		let mut sorter = Sorter::new(32.0, 32.0, 2.0, 8, SortMode::Bidirectional);
+		let ptr = sorter.input_buffer(points.len() as u32);
+		let input = unsafe { std::slice::from_raw_parts_mut(ptr, points.len()) };
+		input.copy_from_slice(&points);
+		let len = sorter.sort_input();
+		assert_eq!(len, sorter.output_len());
+		let output =
+			unsafe { std::slice::from_raw_parts(sorter.output_ptr(), len as usize) };
+		let expected =
+			sort_with_mode(&points, 32.0, 32.0, 2.0, SortMode::Bidirectional);
+		assert_eq!(output, &*expected);
 	}
 }
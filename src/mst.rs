@@ -0,0 +1,457 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use super::coord::{sq_dist, Coord, Indexed};
+use super::quadtree::{Circle, QuadTree, Rectangle, DEFAULT_CAPACITY};
+use super::stroke::Strokes;
+
+/// Minimum number of neighbours each point gets connected to in the graph the
+/// spanning tree is built from
+const MIN_NEIGHBOURS: usize = 8;
+
+struct DisjointSet {
+	parents: Vec<usize>,
+	ranks: Vec<u8>,
+}
+
+impl DisjointSet {
+	fn new(size: usize) -> Self {
+		Self {
+			parents: (0..size).collect(),
+			ranks: vec![0; size],
+		}
+	}
+
+	fn find(&mut self, mut i: usize) -> usize {
+		while self.parents[i] != i {
+			self.parents[i] = self.parents[self.parents[i]];
+			i = self.parents[i];
+		}
+		i
+	}
+
+	/// Merge the sets containing `a` and `b`. Returns `false` if they already
+	/// were in the same set.
+	fn union(&mut self, a: usize, b: usize) -> bool {
+		let a = self.find(a);
+		let b = self.find(b);
+		if a == b {
+			return false;
+		}
+		if self.ranks[a] < self.ranks[b] {
+			self.parents[a] = b;
+		} else if self.ranks[a] > self.ranks[b] {
+			self.parents[b] = a;
+		} else {
+			self.parents[b] = a;
+			self.ranks[a] += 1;
+		}
+		true
+	}
+}
+
+/// Build a euclidean minimum spanning tree over the points and walk it depth
+/// first, such that every branch of the tree becomes a stroke of its own.
+/// Each stroke, except the first one of a tree, starts at the point it
+/// branches off from, so the strokes stay connected.
+///
+/// To keep this fast, the tree is built from a neighbour graph, in which every
+/// point is only connected to at least its `MIN_NEIGHBOURS` closest points.
+/// Clusters of points, that are far apart from all others, can therefore end
+/// up as separate trees, which is exactly where a stroke should end anyway.
+pub fn mst_strokes<T: Coord>(
+	points: Vec<T>,
+	w: f32,
+	h: f32,
+	initial_search_radius: f32,
+) -> Strokes<T> {
+	mst_walk(points, w, h, initial_search_radius).0
+}
+
+/// Order the points in the sequence the depth first walk of `mst_strokes`
+/// visits them in. Unlike the strokes, this contains every point only once.
+pub fn mst_order<T: Coord>(
+	points: Vec<T>,
+	w: f32,
+	h: f32,
+	initial_search_radius: f32,
+) -> Vec<T> {
+	mst_walk(points, w, h, initial_search_radius).1
+}
+
+fn mst_walk<T: Coord>(
+	points: Vec<T>,
+	w: f32,
+	h: f32,
+	initial_search_radius: f32,
+) -> (Strokes<T>, Vec<T>) {
+	let mut mst = MstSort::empty(w, h, initial_search_radius, DEFAULT_CAPACITY);
+	mst.restart(points);
+	mst.step(usize::MAX);
+	(mst.strokes, mst.order)
+}
+
+const NO_PARENT: usize = usize::MAX;
+
+/// What a `MstSort` is busy with. The tree stages hold the node, from which
+/// the search for the next tree continues.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stage {
+	/// Connecting the nodes from the given one on to their neighbours
+	Neighbours(usize),
+	/// Adding the edges to the forest, shortest first
+	Edges,
+	/// Looking for a node from the given one on, that isn't in a walked tree
+	NextTree(usize),
+	/// Searching the node, that is farthest away from the start of the tree
+	Farthest(usize),
+	/// Setting the parent of every node of the tree
+	Root(usize),
+	/// Measuring the heights of the nodes, from the leaves up
+	Heights(usize),
+	/// Walking the tree depth first
+	Walk(usize),
+	Done,
+}
+
+/// The spanning tree sort of `mst_strokes`, split into small operations, so
+/// that it can be spread over multiple frames. Every neighbour query, every
+/// edge added to the forest and every node visited by one of the passes over
+/// the trees is one step.
+pub struct MstSort<T: Coord> {
+	w: f32,
+	h: f32,
+	initial_search_radius: f32,
+	stage: Stage,
+	qt: QuadTree<Indexed<T>>,
+	nodes: Vec<T>,
+	/// The edges of the neighbour graph, that haven't been added yet
+	edges: BinaryHeap<Reverse<(u64, usize, usize)>>,
+	sets: DisjointSet,
+	adjacency: Vec<Vec<usize>>,
+	parents: Vec<usize>,
+	/// Length of the longest path from each node down into its subtree
+	heights: Vec<f64>,
+	/// Nodes still to visit, with their parent and distance, while searching
+	/// the farthest node
+	search: Vec<(usize, usize, f64)>,
+	farthest: (usize, f64),
+	/// Nodes still to root, and the rooted ones, every node after its parent
+	pending: Vec<usize>,
+	ordered: Vec<usize>,
+	/// Branches still to walk, with the point they branch off from
+	branches: Vec<(Option<usize>, usize)>,
+	node: Option<usize>,
+	stroke: Vec<T>,
+	strokes: Strokes<T>,
+	order: Vec<T>,
+}
+
+impl<T: Coord> MstSort<T> {
+	/// Create a sort without any points. Use `restart` to fill it.
+	pub fn empty(
+		w: f32,
+		h: f32,
+		initial_search_radius: f32,
+		capacity: usize,
+	) -> Self {
+		let boundary = Rectangle::new(w / 2.0, h / 2.0, w, h);
+		Self {
+			w,
+			h,
+			initial_search_radius,
+			stage: Stage::Done,
+			qt: QuadTree::with_capacity(boundary, capacity),
+			nodes: Vec::new(),
+			edges: BinaryHeap::new(),
+			sets: DisjointSet::new(0),
+			adjacency: Vec::new(),
+			parents: Vec::new(),
+			heights: Vec::new(),
+			search: Vec::new(),
+			farthest: (0, 0.0),
+			pending: Vec::new(),
+			ordered: Vec::new(),
+			branches: Vec::new(),
+			node: None,
+			stroke: Vec::new(),
+			strokes: Strokes::new(),
+			order: Vec::new(),
+		}
+	}
+
+	/// Throw away the current progress and start sorting a new set of points.
+	/// Points outside of the frame and duplicates are dropped.
+	pub fn restart<I: IntoIterator<Item = T>>(&mut self, points: I) {
+		self.qt.clear();
+		self.nodes.clear();
+		for p in points {
+			let indexed = Indexed {
+				point: p,
+				index: self.nodes.len(),
+			};
+			if self.qt.insert(indexed) {
+				self.nodes.push(p);
+			}
+		}
+		let len = self.nodes.len();
+		self.edges.clear();
+		self.sets = DisjointSet::new(len);
+		self.adjacency.iter_mut().for_each(Vec::clear);
+		self.adjacency.resize(len, Vec::new());
+		self.parents.clear();
+		self.parents.resize(len, NO_PARENT);
+		self.heights.clear();
+		self.heights.resize(len, 0.0);
+		self.search.clear();
+		self.pending.clear();
+		self.ordered.clear();
+		self.branches.clear();
+		self.node = None;
+		self.stroke.clear();
+		self.strokes = Strokes::new();
+		self.order.clear();
+		self.stage = Stage::Neighbours(0);
+	}
+
+	/// Take up to `budget` more steps. Returns `true` once all points are
+	/// sorted.
+	pub fn step(&mut self, budget: usize) -> bool {
+		for _ in 0..budget {
+			if !self.advance() {
+				return true;
+			}
+		}
+		self.is_done()
+	}
+
+	pub fn is_done(&self) -> bool {
+		self.stage == Stage::Done
+	}
+
+	/// Number of points, that haven't been visited by the walk yet
+	pub fn remaining(&self) -> usize {
+		self.nodes.len() - self.order.len()
+	}
+
+	/// The points in the order the walk visited them so far
+	pub fn order(&self) -> &[T] {
+		&self.order
+	}
+
+	pub fn into_order(self) -> Vec<T> {
+		self.order
+	}
+
+	/// Take a single step. Returns `false` if there was nothing left to do.
+	fn advance(&mut self) -> bool {
+		self.stage = match self.stage {
+			Stage::Neighbours(i) if i < self.nodes.len() => {
+				self.connect_neighbours(i);
+				Stage::Neighbours(i + 1)
+			}
+			Stage::Neighbours(_) => Stage::Edges,
+			Stage::Edges => match self.edges.pop() {
+				Some(Reverse((_, a, b))) => {
+					if self.sets.union(a, b) {
+						self.adjacency[a].push(b);
+						self.adjacency[b].push(a);
+					}
+					Stage::Edges
+				}
+				None => Stage::NextTree(0),
+			},
+			Stage::NextTree(start) if start < self.nodes.len() => {
+				if self.parents[start] != NO_PARENT {
+					Stage::NextTree(start + 1)
+				} else {
+					self.search.push((start, start, 0.0));
+					self.farthest = (start, 0.0);
+					Stage::Farthest(start)
+				}
+			}
+			Stage::NextTree(_) => Stage::Done,
+			Stage::Farthest(start) => match self.search.pop() {
+				Some((node, parent, dist)) => {
+					if dist > self.farthest.1 {
+						self.farthest = (node, dist);
+					}
+					for &child in self.adjacency[node].iter() {
+						if child != parent {
+							let length = self.edge_length(node, child);
+							self.search.push((child, node, dist + length));
+						}
+					}
+					Stage::Farthest(start)
+				}
+				// Starting the walk at the farthest node makes the first
+				// stroke follow the longest path through the tree
+				None => {
+					let root = self.farthest.0;
+					self.parents[root] = root;
+					self.pending.push(root);
+					self.branches.push((None, root));
+					Stage::Root(start)
+				}
+			},
+			Stage::Root(start) => match self.pending.pop() {
+				Some(node) => {
+					self.ordered.push(node);
+					for &child in self.adjacency[node].iter() {
+						if self.parents[child] == NO_PARENT {
+							self.parents[child] = node;
+							self.pending.push(child);
+						}
+					}
+					Stage::Root(start)
+				}
+				None => Stage::Heights(start),
+			},
+			Stage::Heights(start) => match self.ordered.pop() {
+				Some(node) => {
+					let parent = self.parents[node];
+					if parent != node {
+						let h = self.heights[node] + self.edge_length(node, parent);
+						if h > self.heights[parent] {
+							self.heights[parent] = h;
+						}
+					}
+					Stage::Heights(start)
+				}
+				None => Stage::Walk(start),
+			},
+			Stage::Walk(start) => {
+				if self.walk_node() {
+					Stage::Walk(start)
+				} else {
+					Stage::NextTree(start + 1)
+				}
+			}
+			Stage::Done => return false,
+		};
+		true
+	}
+
+	/// Connect a node to at least its `MIN_NEIGHBOURS` closest nodes
+	fn connect_neighbours(&mut self, i: usize) {
+		let p = self.nodes[i];
+		let max_radius = (self.w.max(self.h) * 2.0) as f64;
+		let mut search_radius = self.initial_search_radius.max(1.0) as f64;
+		let found = loop {
+			let r = self.qt.query(&Circle::new(p.x(), p.y(), search_radius));
+			if r.len() > MIN_NEIGHBOURS || search_radius >= max_radius {
+				break r;
+			}
+			search_radius *= 2.0;
+		};
+		for neighbour in found {
+			// Only add every edge once. The distances are positive, so their
+			// bits are ordered like them.
+			if neighbour.index > i {
+				let d = sq_dist(&p, &neighbour.point);
+				self.edges.push(Reverse((d.to_bits(), i, neighbour.index)));
+			}
+		}
+	}
+
+	/// Visit the next node of the walk, appending a stroke for every finished
+	/// branch. Follow the highest child to make strokes as long as possible
+	/// and remember all other children as the start of a branch. Returns
+	/// `false` once the tree is walked.
+	fn walk_node(&mut self) -> bool {
+		let node = match self.node {
+			Some(node) => node,
+			None => match self.branches.pop() {
+				Some((branch_point, start)) => {
+					if let Some(b) = branch_point {
+						self.stroke.push(self.nodes[b]);
+					}
+					start
+				}
+				None => return false,
+			},
+		};
+		self.order.push(self.nodes[node]);
+		self.stroke.push(self.nodes[node]);
+		let mut children: Vec<usize> = self.adjacency[node]
+			.iter()
+			.copied()
+			.filter(|&child| child != self.parents[node])
+			.collect();
+		if children.is_empty() {
+			self.strokes.push(std::mem::take(&mut self.stroke));
+			self.node = None;
+			return true;
+		}
+		children
+			.sort_by(|a, b| self.heights[*a].partial_cmp(&self.heights[*b]).unwrap());
+		self.node = children.pop();
+		for child in children {
+			self.branches.push((Some(node), child));
+		}
+		true
+	}
+
+	fn edge_length(&self, a: usize, b: usize) -> f64 {
+		sq_dist(&self.nodes[a], &self.nodes[b]).sqrt()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::Point;
+
+	const P: fn(x: f32, y: f32) -> Point = Point::new;
+
+	#[test]
+	fn line() {
+		let strokes = mst_strokes(
+			vec![P(2.0, 0.0), P(0.0, 0.0), P(3.0, 0.0), P(1.0, 0.0)],
+			10.0,
+			10.0,
+			2.0,
+		);
+		assert_eq!(strokes.len(), 1);
+		let stroke = strokes.get(0);
+		assert!(
+			stroke == [P(0.0, 0.0), P(1.0, 0.0), P(2.0, 0.0), P(3.0, 0.0)] ||
+				stroke == [P(3.0, 0.0), P(2.0, 0.0), P(1.0, 0.0), P(0.0, 0.0)],
+			"{:?}",
+			stroke
+		);
+	}
+
+	#[test]
+	fn branches() {
+		// A "T" shape: a horizontal bar with a short stem in the middle
+		let mut points = Vec::new();
+		for x in 0..=10 {
+			points.push(P(x as f32, 1.0));
+		}
+		for y in 2..=4 {
+			points.push(P(5.0, y as f32));
+		}
+		let strokes = mst_strokes(points.clone(), 12.0, 12.0, 2.0);
+		assert_eq!(strokes.len(), 2);
+		assert_eq!(strokes.get(0).len(), 11);
+		assert_eq!(
+			strokes.get(1),
+			&[P(5.0, 1.0), P(5.0, 2.0), P(5.0, 3.0), P(5.0, 4.0)]
+		);
+		// Every point is visited exactly once, except for the branch point
+		assert_eq!(strokes.points().len(), points.len() + 1);
+	}
+
+	#[test]
+	fn separate_clusters() {
+		let strokes = mst_strokes(
+			vec![P(0.0, 0.0), P(1.0, 0.0), P(90.0, 90.0), P(91.0, 90.0)],
+			100.0,
+			100.0,
+			2.0,
+		);
+		let total: usize = strokes.iter().map(|s| s.len()).sum();
+		assert_eq!(total, 4);
+	}
+}
@@ -0,0 +1,157 @@
+use wasm_bindgen::prelude::*;
+
+use super::coord::{Coord, Point};
+use super::random::Random;
+
+/// How the elbow between two points, that aren't in line, is placed
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PipeRouting {
+	/// Always go horizontally first
+	HorizontalFirst = 0,
+	/// Keep going in the direction the pipe arrived in, which avoids
+	/// needless turns. The first elbow of a stroke goes horizontally first.
+	FollowDirection = 1,
+	/// Pick the direction at random, but always the same one for the same
+	/// two points and seed
+	Hashed = 2,
+}
+
+/// Connect the points with horizontal and vertical lines only, by inserting an
+/// elbow between every two points, that aren't already in line. Points in the
+/// middle of straight runs are left out, so that the route only contains its
+/// corners and ends.
+pub fn route_pipes<T: Coord>(
+	points: &[T],
+	routing: PipeRouting,
+	seed: u64,
+) -> Vec<Point> {
+	let mut route: Vec<Point> = Vec::with_capacity(points.len() * 2);
+	let mut horizontal = true;
+	for (i, p) in points.iter().enumerate() {
+		let p = Point::new(p.x(), p.y());
+		if let Some(prev) = i.checked_sub(1).map(|i| points[i]) {
+			if prev.x() != p.x() && prev.y() != p.y() {
+				let horizontal_first = match routing {
+					PipeRouting::HorizontalFirst => true,
+					PipeRouting::FollowDirection => horizontal,
+					PipeRouting::Hashed => segment_hash(&prev, &p, seed) & 1 == 0,
+				};
+				let elbow = if horizontal_first {
+					Point::new(p.x(), prev.y())
+				} else {
+					Point::new(prev.x(), p.y())
+				};
+				extend(&mut route, elbow);
+				horizontal = !horizontal_first;
+			} else if prev.x() != p.x() || prev.y() != p.y() {
+				horizontal = prev.y() == p.y();
+			}
+		}
+		extend(&mut route, p);
+	}
+	route
+}
+
+/// Append a point to the route, replacing the last one if it lies on a
+/// straight line to the new one
+fn extend(route: &mut Vec<Point>, p: Point) {
+	if route.last() == Some(&p) {
+		return;
+	}
+	if let [.., a, b] = route[..] {
+		let same_direction = |a: f32, b: f32, c: f32| (b - a) * (c - b) > 0.0;
+		let horizontal =
+			a.y() == b.y() && b.y() == p.y() && same_direction(a.x(), b.x(), p.x());
+		let vertical =
+			a.x() == b.x() && b.x() == p.x() && same_direction(a.y(), b.y(), p.y());
+		if horizontal || vertical {
+			route.pop();
+		}
+	}
+	route.push(p);
+}
+
+/// Mix the seed and the coordinates of both points, in order, into one number
+fn segment_hash<A: Coord, B: Coord>(a: &A, b: &B, seed: u64) -> u64 {
+	[a.x(), a.y(), b.x(), b.y()]
+		.iter()
+		.fold(seed, |hash, value| {
+			Random::new(hash ^ u64::from((value + 0.0).to_bits())).next_u64()
+		})
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const P: fn(x: f32, y: f32) -> Point = Point::new;
+
+	fn is_manhattan(route: &[Point]) -> bool {
+		route
+			.windows(2)
+			.all(|w| (w[0].x() == w[1].x()) != (w[0].y() == w[1].y()))
+	}
+
+	#[test]
+	fn elbows() {
+		let route = route_pipes(
+			&[P(0.0, 0.0), P(2.0, 3.0), P(1.0, 5.0), P(1.0, 7.0)],
+			PipeRouting::HorizontalFirst,
+			0,
+		);
+		assert_eq!(
+			route,
+			vec![
+				P(0.0, 0.0),
+				P(2.0, 0.0),
+				P(2.0, 3.0),
+				P(1.0, 3.0),
+				P(1.0, 7.0)
+			]
+		);
+	}
+
+	#[test]
+	fn collinear_runs() {
+		let points = [
+			P(0.0, 0.0),
+			P(1.0, 0.0),
+			P(2.0, 0.0),
+			P(2.0, 0.0),
+			P(3.0, 1.0),
+			P(3.0, 4.0),
+			P(3.0, 2.0),
+		];
+		let route = route_pipes(&points, PipeRouting::HorizontalFirst, 0);
+		// Turning back on the same line is a corner, too
+		assert_eq!(
+			route,
+			vec![P(0.0, 0.0), P(3.0, 0.0), P(3.0, 4.0), P(3.0, 2.0)]
+		);
+	}
+
+	#[test]
+	fn follow_direction() {
+		let points = [P(0.0, 0.0), P(0.0, 2.0), P(3.0, 4.0), P(5.0, 5.0)];
+		let route = route_pipes(&points, PipeRouting::FollowDirection, 0);
+		// The pipe keeps going down, then right, then right again
+		assert_eq!(
+			route,
+			vec![P(0.0, 0.0), P(0.0, 4.0), P(5.0, 4.0), P(5.0, 5.0)]
+		);
+	}
+
+	#[test]
+	fn hashed() {
+		let points: Vec<Point> = (0..50)
+			.map(|i| P(i as f32, ((i * 7) % 11) as f32))
+			.collect();
+		let route = route_pipes(&points, PipeRouting::Hashed, 3);
+		assert!(is_manhattan(&route));
+		assert_eq!(route, route_pipes(&points, PipeRouting::Hashed, 3));
+		assert_ne!(route, route_pipes(&points, PipeRouting::Hashed, 4));
+		// The elbows don't all go the same way
+		assert_ne!(route, route_pipes(&points, PipeRouting::HorizontalFirst, 3));
+	}
+}
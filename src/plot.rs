@@ -0,0 +1,70 @@
+use wasm_bindgen::prelude::*;
+
+use super::coord::Coord;
+
+/// The unit of the lengths of a plot
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Units {
+	Millimeters = 0,
+	Inches = 1,
+}
+
+/// Maps the points of a frame onto the drawable area of a sheet of paper. The
+/// frame is scaled uniformly to fit within the margins, centered, and flipped
+/// vertically, because plotters count y upwards while images count it
+/// downwards.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlotTransform {
+	scale: f32,
+	offset_x: f32,
+	offset_y: f32,
+	frame_height: f32,
+}
+
+impl PlotTransform {
+	pub fn fit(
+		frame_width: f32,
+		frame_height: f32,
+		paper_width: f32,
+		paper_height: f32,
+		margin: f32,
+	) -> Self {
+		let area_width = (paper_width - 2.0 * margin).max(0.0);
+		let area_height = (paper_height - 2.0 * margin).max(0.0);
+		let scale = if frame_width > 0.0 && frame_height > 0.0 {
+			(area_width / frame_width).min(area_height / frame_height)
+		} else {
+			0.0
+		};
+		Self {
+			scale,
+			offset_x: margin + (area_width - frame_width * scale) / 2.0,
+			offset_y: margin + (area_height - frame_height * scale) / 2.0,
+			frame_height,
+		}
+	}
+
+	pub fn apply<T: Coord>(&self, p: &T) -> (f32, f32) {
+		(
+			self.offset_x + p.x() * self.scale,
+			self.offset_y + (self.frame_height - p.y()) * self.scale,
+		)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::coord::Point;
+
+	#[test]
+	fn fit_landscape_frame() {
+		// A 200x100 frame on a 100x100 sheet with a margin of 10 is scaled to
+		// 80x40 and centered vertically
+		let transform = PlotTransform::fit(200.0, 100.0, 100.0, 100.0, 10.0);
+		assert_eq!(transform.apply(&Point::new(0.0, 0.0)), (10.0, 70.0));
+		assert_eq!(transform.apply(&Point::new(200.0, 100.0)), (90.0, 30.0));
+		assert_eq!(transform.apply(&Point::new(100.0, 50.0)), (50.0, 50.0));
+	}
+}
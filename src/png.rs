@@ -0,0 +1,177 @@
+/// The eight bytes every PNG file starts with
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+/// The largest amount of data a stored deflate block can hold
+const MAX_STORED_BLOCK: usize = 0xffff;
+
+/// Encode an 8 bit RGBA image as PNG. The pixel data is stored without
+/// compression, which keeps the encoder small and fast, at the cost of
+/// larger files.
+pub fn encode_png(width: usize, height: usize, rgba: &[u8]) -> Vec<u8> {
+	assert_eq!(rgba.len(), width * height * 4, "wrong image size");
+	let mut png = SIGNATURE.to_vec();
+
+	let mut header = Vec::with_capacity(13);
+	header.extend_from_slice(&(width as u32).to_be_bytes());
+	header.extend_from_slice(&(height as u32).to_be_bytes());
+	// Bit depth 8, color type RGBA, default compression, filtering and no
+	// interlacing
+	header.extend_from_slice(&[8, 6, 0, 0, 0]);
+	write_chunk(&mut png, b"IHDR", &header);
+
+	// Every row starts with its filter type, which is always none
+	let mut scanlines = Vec::with_capacity((width * 4 + 1) * height);
+	for y in 0..height {
+		scanlines.push(0);
+		scanlines.extend_from_slice(&rgba[y * width * 4..(y + 1) * width * 4]);
+	}
+	write_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+	write_chunk(&mut png, b"IEND", &[]);
+	png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+	png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+	let start = png.len();
+	png.extend_from_slice(kind);
+	png.extend_from_slice(data);
+	let crc = crc32(&png[start..]);
+	png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wrap the data in a zlib stream of uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+	let blocks = data.len() / MAX_STORED_BLOCK + 1;
+	let mut zlib = Vec::with_capacity(data.len() + blocks * 5 + 6);
+	// Deflate with a 32K window, and no preset dictionary
+	zlib.extend_from_slice(&[0x78, 0x01]);
+	let mut chunks = data.chunks(MAX_STORED_BLOCK).peekable();
+	if chunks.peek().is_none() {
+		zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+	}
+	while let Some(chunk) = chunks.next() {
+		let is_last = chunks.peek().is_none();
+		zlib.push(is_last as u8);
+		let len = chunk.len() as u16;
+		zlib.extend_from_slice(&len.to_le_bytes());
+		zlib.extend_from_slice(&(!len).to_le_bytes());
+		zlib.extend_from_slice(chunk);
+	}
+	zlib.extend_from_slice(&adler32(data).to_be_bytes());
+	zlib
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+	let mut crc = !0u32;
+	for &byte in data {
+		crc ^= u32::from(byte);
+		for _ in 0..8 {
+			let mask = (crc & 1).wrapping_neg();
+			crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+		}
+	}
+	!crc
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+	const MOD: u32 = 65521;
+	let (mut a, mut b) = (1u32, 0u32);
+	// The sums can't overflow within this many bytes
+	for chunk in data.chunks(5552) {
+		for &byte in chunk {
+			a += u32::from(byte);
+			b += a;
+		}
+		a %= MOD;
+		b %= MOD;
+	}
+	(b << 16) | a
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// Split a PNG file into its chunks, checking their CRCs
+	fn chunks(png: &[u8]) -> Vec<(String, Vec<u8>)> {
+		assert_eq!(png[..8], SIGNATURE);
+		let mut chunks = Vec::new();
+		let mut rest = &png[8..];
+		while !rest.is_empty() {
+			let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+			let body = &rest[4..8 + len];
+			let crc = u32::from_be_bytes(rest[8 + len..12 + len].try_into().unwrap());
+			assert_eq!(crc, crc32(body));
+			let kind = String::from_utf8(body[..4].to_vec()).unwrap();
+			chunks.push((kind, body[4..].to_vec()));
+			rest = &rest[12 + len..];
+		}
+		chunks
+	}
+
+	/// Read back a zlib stream of stored blocks
+	fn inflate_stored(zlib: &[u8]) -> Vec<u8> {
+		assert_eq!(zlib[..2], [0x78, 0x01]);
+		assert_eq!(u16::from_be_bytes([zlib[0], zlib[1]]) % 31, 0);
+		let mut data = Vec::new();
+		let mut rest = &zlib[2..];
+		loop {
+			let is_last = rest[0] == 1;
+			let len = u16::from_le_bytes([rest[1], rest[2]]);
+			let nlen = u16::from_le_bytes([rest[3], rest[4]]);
+			assert_eq!(len, !nlen);
+			data.extend_from_slice(&rest[5..5 + len as usize]);
+			rest = &rest[5 + len as usize..];
+			if is_last {
+				break;
+			}
+		}
+		assert_eq!(rest, adler32(&data).to_be_bytes());
+		data
+	}
+
+	#[test]
+	fn checksums() {
+		assert_eq!(crc32(b""), 0);
+		assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+		assert_eq!(adler32(b""), 1);
+		assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+		// Long enough for the sums to need the modulo
+		let data = vec![0xff; 100_000];
+		let (mut a, mut b) = (1u64, 0u64);
+		for &byte in &data {
+			a = (a + byte as u64) % 65521;
+			b = (b + a) % 65521;
+		}
+		assert_eq!(adler32(&data), ((b << 16) | a) as u32);
+	}
+
+	#[test]
+	fn small_image() {
+		let rgba = [255, 0, 0, 255, 0, 255, 0, 128, 0, 0, 255, 0, 9, 9, 9, 9];
+		let png = encode_png(2, 2, &rgba);
+		let chunks = chunks(&png);
+		let kinds: Vec<&str> =
+			chunks.iter().map(|(kind, _)| kind.as_str()).collect();
+		assert_eq!(kinds, ["IHDR", "IDAT", "IEND"]);
+		assert_eq!(chunks[0].1, [0, 0, 0, 2, 0, 0, 0, 2, 8, 6, 0, 0, 0]);
+		assert_eq!(
+			inflate_stored(&chunks[1].1),
+			[0, 255, 0, 0, 255, 0, 255, 0, 128, 0, 0, 0, 255, 0, 9, 9, 9, 9]
+		);
+	}
+
+	#[test]
+	fn large_image() {
+		// Big enough for several deflate blocks
+		let (width, height) = (300, 200);
+		let rgba: Vec<u8> =
+			(0..width * height * 4).map(|i| (i % 251) as u8).collect();
+		let png = encode_png(width, height, &rgba);
+		let data = inflate_stored(&chunks(&png)[1].1);
+		assert_eq!(data.len(), (width * 4 + 1) * height);
+		for (y, row) in data.chunks(width * 4 + 1).enumerate() {
+			assert_eq!(row[0], 0);
+			assert_eq!(row[1..], rgba[y * width * 4..(y + 1) * width * 4]);
+		}
+	}
+}
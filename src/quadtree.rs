@@ -37,6 +37,28 @@ impl Rectangle {
 		}
 	}
 
+	/// The rectangle around all points, with a margin, so that none of them
+	/// lies on its edge
+	pub fn around<T: Coord>(points: &[T]) -> Self {
+		if points.is_empty() {
+			return Self::new(0.0, 0.0, 1.0, 1.0);
+		}
+		let (mut min_x, mut min_y) = (f32::MAX, f32::MAX);
+		let (mut max_x, mut max_y) = (f32::MIN, f32::MIN);
+		for p in points {
+			min_x = min_x.min(p.x());
+			min_y = min_y.min(p.y());
+			max_x = max_x.max(p.x());
+			max_y = max_y.max(p.y());
+		}
+		Self::new(
+			(min_x + max_x) / 2.0,
+			(min_y + max_y) / 2.0,
+			max_x - min_x + 2.0,
+			max_y - min_y + 2.0,
+		)
+	}
+
 	pub fn subdivide(&self, quadrant: &Quadrant) -> Self {
 		match quadrant {
 			Quadrant::NorthEast => Self::new(
@@ -65,6 +87,13 @@ impl Rectangle {
 			),
 		}
 	}
+
+	/// Squared distance from a point to the closest point of the rectangle
+	fn sq_dist_to(&self, x: f32, y: f32) -> f64 {
+		let dx = (self.left - x).max(0.0).max(x - self.right);
+		let dy = (self.top - y).max(0.0).max(y - self.bottom);
+		(dx * dx + dy * dy) as f64
+	}
 }
 
 impl Queryable for Rectangle {
@@ -137,7 +166,7 @@ impl Queryable for Circle {
 	}
 }
 
-const CAPACITY: usize = 8;
+pub const DEFAULT_CAPACITY: usize = 8;
 
 pub struct QuadTree<T: Coord> {
 	root: Box<Node<T>>,
@@ -147,7 +176,7 @@ pub struct Node<T: Coord> {
 	size: usize,
 	own_size: usize,
 	boundary: Rectangle,
-	points: [Option<T>; CAPACITY],
+	points: Vec<Option<T>>,
 	divided: bool,
 	northeast: Option<Box<Node<T>>>,
 	northwest: Option<Box<Node<T>>>,
@@ -156,12 +185,12 @@ pub struct Node<T: Coord> {
 }
 
 impl<T: Coord> Node<T> {
-	fn new(boundary: Rectangle) -> Self {
+	fn new(boundary: Rectangle, capacity: usize) -> Self {
 		Self {
 			size: 0,
 			own_size: 0,
 			boundary,
-			points: [None; CAPACITY],
+			points: vec![None; capacity.max(1)],
 			divided: false,
 			northeast: None,
 			northwest: None,
@@ -171,17 +200,22 @@ impl<T: Coord> Node<T> {
 	}
 
 	fn subdivide(&mut self) {
+		let capacity = self.points.len();
 		self.northeast = Some(Box::new(Self::new(
 			self.boundary.subdivide(&Quadrant::NorthEast),
+			capacity,
 		)));
 		self.northwest = Some(Box::new(Self::new(
 			self.boundary.subdivide(&Quadrant::NorthWest),
+			capacity,
 		)));
 		self.southeast = Some(Box::new(Self::new(
 			self.boundary.subdivide(&Quadrant::SouthEast),
+			capacity,
 		)));
 		self.southwest = Some(Box::new(Self::new(
 			self.boundary.subdivide(&Quadrant::SouthWest),
+			capacity,
 		)));
 
 		self.divided = true;
@@ -216,7 +250,7 @@ impl<T: Coord> Node<T> {
 			return false;
 		}
 
-		if self.own_size < CAPACITY {
+		if self.own_size < self.points.len() {
 			let insertion_index =
 				self.points.iter().position(|p| p.is_none()).expect(
 					"We've checked before, if there is space left for a new point, so \
@@ -295,15 +329,92 @@ impl<T: Coord> Node<T> {
 		self.southeast.as_ref().unwrap().query(range, found);
 	}
 
+	fn nearest(&self, x: f32, y: f32, best: &mut Option<(T, f64)>) {
+		if let Some((_, best_dist)) = best {
+			if self.boundary.sq_dist_to(x, y) > *best_dist {
+				return;
+			}
+		}
+
+		for p in self.points.iter().flatten() {
+			let dx = p.x() - x;
+			let dy = p.y() - y;
+			let d = (dx * dx + dy * dy) as f64;
+			let is_closer = match best {
+				None => true,
+				// Break ties in favour of the point that comes first in scan
+				// order, so the result does not depend on the shape of the tree
+				Some((best_point, best_dist)) => {
+					d < *best_dist ||
+						(d == *best_dist &&
+							(p.y(), p.x()) < (best_point.y(), best_point.x()))
+				}
+			};
+			if is_closer {
+				*best = Some((*p, d));
+			}
+		}
+		if !self.divided {
+			return;
+		}
+
+		// Visit the children closest to the search point first, so that the
+		// best distance shrinks quickly and the remaining children get pruned
+		let mut children = [
+			self.northwest.as_ref().unwrap(),
+			self.northeast.as_ref().unwrap(),
+			self.southwest.as_ref().unwrap(),
+			self.southeast.as_ref().unwrap(),
+		];
+		children.sort_by(|a, b| {
+			a.boundary
+				.sq_dist_to(x, y)
+				.partial_cmp(&b.boundary.sq_dist_to(x, y))
+				.unwrap()
+		});
+		for child in children.iter() {
+			if child.size > 0 {
+				child.nearest(x, y, best);
+			}
+		}
+	}
+
 	fn size(&self) -> usize {
 		self.size
 	}
+
+	/// Remove all points, but keep the subdivisions, so they don't need to be
+	/// allocated again, when the tree is refilled
+	fn clear(&mut self) {
+		if self.size == 0 {
+			return;
+		}
+		for p in self.points.iter_mut() {
+			*p = None;
+		}
+		self.own_size = 0;
+		self.size = 0;
+		if !self.divided {
+			return;
+		}
+
+		self.northwest.as_mut().unwrap().clear();
+		self.northeast.as_mut().unwrap().clear();
+		self.southwest.as_mut().unwrap().clear();
+		self.southeast.as_mut().unwrap().clear();
+	}
 }
 
 impl<T: Coord> QuadTree<T> {
 	pub fn new(boundary: Rectangle) -> Self {
+		Self::with_capacity(boundary, DEFAULT_CAPACITY)
+	}
+
+	/// Create a tree, whose nodes each hold up to `capacity` points before
+	/// they are subdivided
+	pub fn with_capacity(boundary: Rectangle, capacity: usize) -> Self {
 		Self {
-			root: Box::new(Node::new(boundary)),
+			root: Box::new(Node::new(boundary, capacity)),
 		}
 	}
 
@@ -325,9 +436,20 @@ impl<T: Coord> QuadTree<T> {
 		found
 	}
 
+	/// Find the point closest to `(x, y)`, or `None` if the tree is empty
+	pub fn nearest(&self, x: f32, y: f32) -> Option<T> {
+		let mut best = None;
+		self.root.nearest(x, y, &mut best);
+		best.map(|(p, _)| p)
+	}
+
 	pub fn size(&self) -> usize {
 		self.root.size()
 	}
+
+	pub fn clear(&mut self) {
+		self.root.clear();
+	}
 }
 
 #[cfg(test)]
@@ -415,6 +537,40 @@ mod test {
 		assert_eq!(qt.size(), 0);
 	}
 
+	#[test]
+	fn qt_clear() {
+		let mut qt =
+			QuadTree::with_capacity(Rectangle::new(20.0, 20.0, 40.0, 40.0), 2);
+		for i in 0..10 {
+			qt.insert(Point::new(i as f32 * 3.0, i as f32 * 3.0));
+		}
+		assert_eq!(qt.size(), 10);
+		qt.clear();
+		assert_eq!(qt.size(), 0);
+		assert!(!qt.contains(&Point::new(3.0, 3.0)));
+		assert_eq!(qt.query(&Rectangle::new(20.0, 20.0, 40.0, 40.0)), vec![]);
+		for i in 0..10 {
+			qt.insert(Point::new(i as f32 * 2.0, 1.0));
+		}
+		assert_eq!(qt.size(), 10);
+		assert_eq!(qt.nearest(5.0, 0.0), Some(Point::new(4.0, 1.0)));
+	}
+
+	#[test]
+	fn qt_custom_capacity() {
+		let mut qt = QuadTree::<Point>::with_capacity(
+			Rectangle::new(20.0, 20.0, 40.0, 40.0),
+			2,
+		);
+		assert_eq!(qt.size(), 0);
+		// Nodes holding only two points are subdivided early on
+		for i in 0..10 {
+			qt.insert(Point::new(i as f32 * 3.0, 5.0));
+		}
+		assert_eq!(qt.size(), 10);
+		assert_eq!(qt.nearest(10.0, 6.0), Some(Point::new(9.0, 5.0)));
+	}
+
 	#[test]
 	fn qt_contains() {
 		let mut qt = QuadTree::new(Rectangle::new(20.0, 20.0, 40.0, 40.0));
@@ -595,4 +751,31 @@ mod test {
 			"5.0, 5.0, 1.0, 1.0",
 		);
 	}
+
+	#[test]
+	fn qt_nearest() {
+		let mut qt = QuadTree::new(Rectangle::new(20.0, 20.0, 40.0, 40.0));
+		assert_eq!(qt.nearest(10.0, 10.0), None);
+		let points = vec![
+			Point::new(1.0, 1.0),
+			Point::new(10.0, 12.0),
+			Point::new(21.0, 20.0),
+			Point::new(39.0, 2.0),
+			Point::new(30.0, 30.0),
+			Point::new(2.0, 38.0),
+			Point::new(18.0, 25.0),
+			Point::new(25.0, 5.0),
+			Point::new(33.0, 15.0),
+			Point::new(12.0, 33.0),
+		];
+		for p in points.iter() {
+			qt.insert(*p);
+		}
+		assert_eq!(qt.nearest(10.0, 10.0), Some(Point::new(10.0, 12.0)));
+		assert_eq!(qt.nearest(20.0, 20.0), Some(Point::new(21.0, 20.0)));
+		assert_eq!(qt.nearest(40.0, 0.0), Some(Point::new(39.0, 2.0)));
+		assert_eq!(qt.nearest(-5.0, 45.0), Some(Point::new(2.0, 38.0)));
+		qt.remove(&Point::new(21.0, 20.0));
+		assert_eq!(qt.nearest(20.0, 20.0), Some(Point::new(18.0, 25.0)));
+	}
 }
@@ -0,0 +1,44 @@
+/// A small, seedable pseudo random number generator (SplitMix64). The same
+/// seed always produces the same sequence, so that results can be reproduced.
+#[derive(Clone, Debug)]
+pub struct Random {
+	state: u64,
+}
+
+impl Random {
+	pub fn new(seed: u64) -> Self {
+		Self { state: seed }
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+		let mut z = self.state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+		z ^ (z >> 31)
+	}
+
+	/// A number between 0 (inclusive) and 1 (exclusive)
+	pub fn next_f64(&mut self) -> f64 {
+		(self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn sequence() {
+		let mut random = Random::new(0);
+		assert_eq!(random.next_u64(), 0xe220_a839_7b1d_cdaf);
+		assert_eq!(random.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+		let mut a = Random::new(42);
+		let mut b = Random::new(42);
+		for _ in 0..100 {
+			let value = a.next_f64();
+			assert_eq!(value, b.next_f64());
+			assert!((0.0..1.0).contains(&value));
+		}
+	}
+}
@@ -0,0 +1,439 @@
+use super::coord::{Coord, Point};
+use super::png::encode_png;
+use super::simplify::segment_dist;
+use super::stroke::Strokes;
+use super::style::DrawStyle;
+use super::width::{outline, StrengthMap, StrokeWidths, WidthSource};
+
+/// Number of times each row of pixels is sampled when filling polygons
+const ROW_SAMPLES: usize = 4;
+
+/// An RGBA image to draw strokes on, with anti-aliased edges. Pixel `(x, y)`
+/// covers the square from `(x, y)` to `(x + 1, y + 1)`.
+#[derive(Clone, Debug)]
+pub struct Canvas {
+	width: usize,
+	height: usize,
+	/// Colors and opacity, from 0 to 1, without premultiplied alpha
+	pixels: Vec<[f32; 4]>,
+	/// How much of each pixel the path being drawn covers. Overlapping parts
+	/// of a path are only painted once, like in SVG.
+	coverage: Vec<f32>,
+	/// The pixels with a coverage above 0
+	covered: Vec<usize>,
+}
+
+impl Canvas {
+	/// A fully transparent canvas
+	pub fn new(width: usize, height: usize) -> Self {
+		Self {
+			width,
+			height,
+			pixels: vec![[0.0; 4]; width * height],
+			coverage: vec![0.0; width * height],
+			covered: Vec::new(),
+		}
+	}
+
+	/// Paint a gray value over the whole canvas. With an opacity below 1,
+	/// earlier drawings fade out instead of being covered completely, like
+	/// the background of the app between frames.
+	pub fn fade(&mut self, gray: u8, opacity: f32) {
+		let color = f32::from(gray) / 255.0;
+		for pixel in &mut self.pixels {
+			blend(pixel, color, opacity);
+		}
+	}
+
+	/// Draw the strokes in the given style on top of the canvas. The
+	/// background isn't painted. The strength map is only needed for lines,
+	/// whose width depends on the edge strength.
+	pub fn draw<T: Coord>(
+		&mut self,
+		strokes: &Strokes<T>,
+		style: &DrawStyle,
+		strength: Option<&StrengthMap>,
+	) {
+		let (_, foreground) = style.colors();
+		let parts = style.parts(strokes);
+		let widths = StrokeWidths::new(&parts, style, strength);
+		let radius = style.stroke_width / 2.0;
+		for part in parts.iter() {
+			for line in style.shapes(part) {
+				if style.width_source != WidthSource::Constant {
+					self.add_polygon(&outline(&line, &widths.widths(&line)));
+				} else if let [p] = line[..] {
+					// A line of zero length with round caps makes a dot
+					self.add_line(&p, &p, radius);
+				} else {
+					self.add_polyline(&line, radius);
+				}
+			}
+			self.paint_path(foreground, style.stroke_opacity);
+		}
+	}
+
+	/// The pixels in rows, with 4 bytes per pixel, like the `data` of an
+	/// `ImageData` object
+	pub fn to_rgba(&self) -> Vec<u8> {
+		self
+			.pixels
+			.iter()
+			.flat_map(|pixel| pixel.map(|value| (value * 255.0).round() as u8))
+			.collect()
+	}
+
+	pub fn to_png(&self) -> Vec<u8> {
+		encode_png(self.width, self.height, &self.to_rgba())
+	}
+
+	/// Add lines between the points to the path
+	fn add_polyline(&mut self, points: &[Point], radius: f32) {
+		for pair in points.windows(2) {
+			self.add_line(&pair[0], &pair[1], radius);
+		}
+	}
+
+	/// Add a line with round caps to the path
+	fn add_line(&mut self, a: &Point, b: &Point, radius: f32) {
+		// Pixels farther from the line than this aren't covered at all
+		let reach = radius + 0.5;
+		let range = |a: f32, b: f32, size: usize| {
+			let start = (a.min(b) - reach).floor().max(0.0) as usize;
+			let end = ((a.max(b) + reach).ceil().max(0.0) as usize).min(size);
+			start..end
+		};
+		for y in range(a.y(), b.y(), self.height) {
+			for x in range(a.x(), b.x(), self.width) {
+				let center = Point::new(x as f32 + 0.5, y as f32 + 0.5);
+				let coverage = (reach - segment_dist(&center, a, b)).clamp(0.0, 1.0);
+				let i = x + y * self.width;
+				if coverage > self.coverage[i] {
+					if self.coverage[i] == 0.0 {
+						self.covered.push(i);
+					}
+					self.coverage[i] = coverage;
+				}
+			}
+		}
+	}
+
+	/// Add a polygon, filled with the nonzero rule, to the path. Its edges are
+	/// smoothed by sampling every row of pixels several times.
+	fn add_polygon(&mut self, polygon: &[Point]) {
+		let Some(top) = polygon.iter().map(|p| p.y()).reduce(f32::min) else {
+			return;
+		};
+		let bottom = polygon.iter().map(|p| p.y()).fold(top, f32::max);
+		let start = top.floor().max(0.0) as usize;
+		let end = (bottom.ceil().max(0.0) as usize).min(self.height);
+		let edges: Vec<(&Point, &Point)> = polygon
+			.iter()
+			.zip(polygon.iter().cycle().skip(1))
+			.filter(|(a, b)| a.y() != b.y())
+			.collect();
+		let mut row = vec![0.0; self.width];
+		let mut crossings = Vec::new();
+		for y in start..end {
+			for sample in 0..ROW_SAMPLES {
+				let sample_y = y as f32 + (sample as f32 + 0.5) / ROW_SAMPLES as f32;
+				crossings.clear();
+				for (a, b) in &edges {
+					let (upper, lower) = if a.y() < b.y() { (a, b) } else { (b, a) };
+					if upper.y() <= sample_y && sample_y < lower.y() {
+						let t = (sample_y - a.y()) / (b.y() - a.y());
+						let winding = if a.y() < b.y() { 1 } else { -1 };
+						crossings.push((a.x() + (b.x() - a.x()) * t, winding));
+					}
+				}
+				crossings.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+				let mut winding = 0;
+				for pair in crossings.windows(2) {
+					winding += pair[0].1;
+					if winding != 0 {
+						add_span(&mut row, pair[0].0, pair[1].0, 1.0 / ROW_SAMPLES as f32);
+					}
+				}
+			}
+			for (x, coverage) in row.iter_mut().enumerate() {
+				let i = x + y * self.width;
+				let value = coverage.min(1.0);
+				if value > self.coverage[i] {
+					if self.coverage[i] == 0.0 {
+						self.covered.push(i);
+					}
+					self.coverage[i] = value;
+				}
+				*coverage = 0.0;
+			}
+		}
+	}
+
+	/// Paint the path added since the last call, and start a new one
+	fn paint_path(&mut self, gray: u8, opacity: f32) {
+		let color = f32::from(gray) / 255.0;
+		for &i in &self.covered {
+			blend(&mut self.pixels[i], color, opacity * self.coverage[i]);
+			self.coverage[i] = 0.0;
+		}
+		self.covered.clear();
+	}
+}
+
+/// Add `amount` to the pixels of a row between `from` and `to`, in proportion
+/// to how much of each pixel lies between them
+fn add_span(row: &mut [f32], from: f32, to: f32, amount: f32) {
+	let from = from.max(0.0);
+	let to = to.min(row.len() as f32);
+	if from >= to {
+		return;
+	}
+	for x in from.floor() as usize..(to.ceil() as usize).min(row.len()) {
+		let overlap = to.min(x as f32 + 1.0) - from.max(x as f32);
+		row[x] += overlap * amount;
+	}
+}
+
+/// Paint a gray value with the given opacity over a pixel
+fn blend(pixel: &mut [f32; 4], color: f32, opacity: f32) {
+	let opacity = opacity.clamp(0.0, 1.0);
+	let below = pixel[3] * (1.0 - opacity);
+	let alpha = opacity + below;
+	if alpha > 0.0 {
+		for channel in &mut pixel[..3] {
+			*channel = (color * opacity + *channel * below) / alpha;
+		}
+	}
+	pixel[3] = alpha;
+}
+
+/// Draw the strokes on a new canvas of the style's size, on top of the
+/// background
+pub fn render<T: Coord>(
+	strokes: &Strokes<T>,
+	style: &DrawStyle,
+	strength: Option<&StrengthMap>,
+) -> Canvas {
+	let mut canvas = Canvas::new(
+		style.width.ceil().max(0.0) as usize,
+		style.height.ceil().max(0.0) as usize,
+	);
+	let (background, _) = style.colors();
+	canvas.fade(background, style.background_opacity);
+	canvas.draw(strokes, style, strength);
+	canvas
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::style::DrawingMode;
+
+	const P: fn(x: f32, y: f32) -> Point = Point::new;
+
+	/// Points in the middle of pixels, so that a line along a row covers that
+	/// row fully. Edge points lie on the corners of pixels instead, where such
+	/// a line would cover two rows by half.
+	fn strokes() -> Strokes<Point> {
+		Strokes::from_parts(
+			vec![
+				P(2.5, 2.5),
+				P(6.5, 7.5),
+				P(12.5, 2.5),
+				P(17.5, 7.5),
+				P(21.5, 2.5),
+				P(21.5, 7.5),
+			],
+			&[0, 5],
+		)
+	}
+
+	fn style(mode: DrawingMode) -> DrawStyle {
+		DrawStyle {
+			mode,
+			stroke_width: 1.5,
+			..DrawStyle::new(24.0, 10.0)
+		}
+	}
+
+	/// The canvas as text, with darker characters for darker pixels
+	fn ascii(canvas: &Canvas) -> Vec<String> {
+		const SHADES: [char; 5] = [' ', '.', '+', '#', '@'];
+		let rgba = canvas.to_rgba();
+		rgba
+			.chunks(canvas.width * 4)
+			.map(|row| {
+				row
+					.chunks(4)
+					.map(|pixel| {
+						assert_eq!(pixel[3], 255);
+						let darkness = 1.0 - f32::from(pixel[0]) / 255.0;
+						SHADES[(darkness * 4.0).round() as usize]
+					})
+					.collect()
+			})
+			.collect()
+	}
+
+	#[test]
+	fn lines() {
+		let canvas = render(&strokes(), &style(DrawingMode::Lines), None);
+		let expected = [
+			"                        ",
+			"  .         .        .  ",
+			" .@+       +@+      +@. ",
+			"  +@.     #@+@+    .@+  ",
+			"   #@.  .#@. +@+  .@#   ",
+			"   .@# .@#.   +@+ #@.   ",
+			"    .@+@#      +@+@.    ",
+			"     +@+        +@+     ",
+			"      .          .      ",
+			"                        ",
+		];
+		assert_eq!(ascii(&canvas), expected);
+	}
+
+	#[test]
+	fn points() {
+		let canvas = render(&strokes(), &style(DrawingMode::Points), None);
+		let expected = [
+			"                        ",
+			"  .         .        .  ",
+			" .@.       .@.      .@. ",
+			"  .         .        .  ",
+			"                        ",
+			"                        ",
+			"      .          .   .  ",
+			"     .@.        .@. .@. ",
+			"      .          .   .  ",
+			"                        ",
+		];
+		assert_eq!(ascii(&canvas), expected);
+	}
+
+	#[test]
+	fn pipes() {
+		let canvas = render(&strokes(), &style(DrawingMode::Pipes), None);
+		let expected = [
+			"                        ",
+			"  .....     ......   .  ",
+			" .@@@@@.   .@@@@@@. .@. ",
+			"  ....@.   .@....@. .@. ",
+			"     .@.   .@.  .@. .@. ",
+			"     .@.   .@.  .@. .@. ",
+			"     .@.....@.  .@...@. ",
+			"     .@@@@@@@.  .@@@@@. ",
+			"      .......    .....  ",
+			"                        ",
+		];
+		assert_eq!(ascii(&canvas), expected);
+	}
+
+	#[test]
+	fn curve() {
+		let canvas = render(&strokes(), &style(DrawingMode::Curve), None);
+		let expected = [
+			"                        ",
+			"            .           ",
+			"          .@@@.         ",
+			"         .@#.##         ",
+			"        .@#  .@#        ",
+			"       .@#.   +@+       ",
+			"      .#@.     #@.      ",
+			"     .@#.       #@.     ",
+			"      .          .      ",
+			"                        ",
+		];
+		assert_eq!(ascii(&canvas), expected);
+	}
+
+	#[test]
+	fn tapered() {
+		let style = DrawStyle {
+			stroke_width: 3.0,
+			min_stroke_width: 0.5,
+			taper_length: 8.0,
+			width_source: WidthSource::Taper,
+			..style(DrawingMode::Lines)
+		};
+		let line = Strokes::single(vec![P(1.5, 4.5), P(12.5, 4.5), P(22.5, 4.5)]);
+		let canvas = render(&line, &style, None);
+		let expected = [
+			"                        ",
+			"                        ",
+			"                        ",
+			"     ..++##@@##+++..    ",
+			" .#@@@@@@@@@@@@@@@@@@#. ",
+			"     ..++##@@##+++..    ",
+			"                        ",
+			"                        ",
+			"                        ",
+			"                        ",
+		];
+		assert_eq!(ascii(&canvas), expected);
+	}
+
+	#[test]
+	fn outlines_match_lines() {
+		// Outlines of a constant width look like lines of that width. Only
+		// the anti-aliasing differs a little, mostly inside sharp turns.
+		for mode in [DrawingMode::Lines, DrawingMode::Points, DrawingMode::Curve] {
+			let style = DrawStyle {
+				stroke_width: 2.0,
+				min_stroke_width: 2.0,
+				..style(mode)
+			};
+			let lines = render(&strokes(), &style, None).to_rgba();
+			let style = DrawStyle {
+				width_source: WidthSource::Taper,
+				..style
+			};
+			let outlines = render(&strokes(), &style, None).to_rgba();
+			for (a, b) in lines.iter().zip(&outlines) {
+				assert!(a.abs_diff(*b) < 64, "{:?}: {} and {}", mode, a, b);
+			}
+		}
+	}
+
+	#[test]
+	fn opacity_and_fade() {
+		let style = DrawStyle {
+			stroke_opacity: 0.5,
+			background_opacity: 0.25,
+			invert_colors: true,
+			..style(DrawingMode::Lines)
+		};
+		let mut canvas = render(
+			&Strokes::single(vec![P(1.5, 5.5), P(20.5, 5.5)]),
+			&style,
+			None,
+		);
+		// A single path is painted once, even where its lines overlap
+		let back_and_forth =
+			Strokes::single(vec![P(1.5, 8.5), P(20.5, 8.5), P(1.5, 8.5)]);
+		canvas.draw(&back_and_forth, &style, None);
+		let rgba = canvas.to_rgba();
+		let pixel =
+			|x: usize, y: usize| &rgba[(x + y * 24) * 4..(x + y * 24 + 1) * 4];
+		// The black background is only a quarter opaque, and the white line
+		// is half opaque on top of it
+		assert_eq!(pixel(0, 0), [0, 0, 0, 64]);
+		assert_eq!(pixel(10, 5), [204, 204, 204, 159]);
+		assert_eq!(pixel(10, 8), pixel(10, 5));
+		// Fading paints the background over everything
+		canvas.fade(0, 1.0);
+		assert_eq!(canvas.to_rgba()[(10 + 5 * 24) * 4..][..4], [0, 0, 0, 255]);
+	}
+
+	#[test]
+	fn png() {
+		let canvas = render(&strokes(), &style(DrawingMode::Lines), None);
+		let png = canvas.to_png();
+		assert_eq!(
+			png[..8],
+			[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n']
+		);
+		// The width and height in the header
+		assert_eq!(png[16..24], [0, 0, 0, 24, 0, 0, 0, 10]);
+	}
+}
@@ -0,0 +1,245 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use wasm_bindgen::prelude::*;
+
+use super::coord::Coord;
+use super::stroke::Strokes;
+
+/// The algorithm used to remove vertices from strokes
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimplifyMethod {
+	/// Keep the vertex farthest from the line between the ends, and repeat for
+	/// both halves, until all vertices are close enough
+	RamerDouglasPeucker = 0,
+	/// Remove the vertex, that spans the smallest triangle with its
+	/// neighbours, as long as the line stays close enough
+	VisvalingamWhyatt = 1,
+}
+
+/// Remove vertices from every stroke, such that no point of the original
+/// stroke is farther than `tolerance` from the simplified one, and vice versa.
+/// The ends of the strokes are always kept. Returns the simplified strokes and
+/// the number of removed vertices.
+pub fn simplify<T: Coord>(
+	strokes: &Strokes<T>,
+	tolerance: f32,
+	method: SimplifyMethod,
+) -> (Strokes<T>, usize) {
+	let mut simplified = Strokes::new();
+	for stroke in strokes.iter() {
+		simplified.push(simplify_polyline(stroke, tolerance, method));
+	}
+	let removed = strokes.points().len() - simplified.points().len();
+	(simplified, removed)
+}
+
+pub fn simplify_polyline<T: Coord>(
+	points: &[T],
+	tolerance: f32,
+	method: SimplifyMethod,
+) -> Vec<T> {
+	if points.len() <= 2 {
+		return points.to_vec();
+	}
+	let keep = match method {
+		SimplifyMethod::RamerDouglasPeucker => {
+			ramer_douglas_peucker(points, tolerance)
+		}
+		SimplifyMethod::VisvalingamWhyatt => visvalingam_whyatt(points, tolerance),
+	};
+	points
+		.iter()
+		.zip(keep)
+		.filter(|(_, keep)| *keep)
+		.map(|(p, _)| *p)
+		.collect()
+}
+
+fn ramer_douglas_peucker<T: Coord>(points: &[T], tolerance: f32) -> Vec<bool> {
+	let mut keep = vec![false; points.len()];
+	keep[0] = true;
+	keep[points.len() - 1] = true;
+	let mut stack = vec![(0, points.len() - 1)];
+	while let Some((first, last)) = stack.pop() {
+		let farthest = (first + 1..last)
+			.map(|i| (i, segment_dist(&points[i], &points[first], &points[last])))
+			.max_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+		if let Some((i, dist)) = farthest {
+			if dist > tolerance {
+				keep[i] = true;
+				stack.push((first, i));
+				stack.push((i, last));
+			}
+		}
+	}
+	keep
+}
+
+fn visvalingam_whyatt<T: Coord>(points: &[T], tolerance: f32) -> Vec<bool> {
+	let n = points.len();
+	let mut keep = vec![true; n];
+	let mut prev: Vec<usize> = (0..n).map(|i| i.saturating_sub(1)).collect();
+	let mut next: Vec<usize> = (0..n).map(|i| (i + 1).min(n - 1)).collect();
+	// Areas are never negative, so their bits sort like the numbers. Entries
+	// whose area is outdated are recognized by their version and skipped.
+	let mut versions = vec![0usize; n];
+	let mut heap = BinaryHeap::new();
+	let area = |i: usize, prev: &[usize], next: &[usize]| {
+		triangle_area(&points[prev[i]], &points[i], &points[next[i]])
+	};
+	for i in 1..n - 1 {
+		heap.push(Reverse((area(i, &prev, &next).to_bits(), i, 0)));
+	}
+
+	while let Some(Reverse((_, i, version))) = heap.pop() {
+		if !keep[i] || version != versions[i] {
+			continue;
+		}
+		let (before, after) = (prev[i], next[i]);
+		// The vertices removed between the neighbours so far and this one
+		// have to stay close to the new segment
+		let within_tolerance = (before + 1..after).all(|j| {
+			segment_dist(&points[j], &points[before], &points[after]) <= tolerance
+		});
+		if !within_tolerance {
+			continue;
+		}
+		keep[i] = false;
+		next[before] = after;
+		prev[after] = before;
+		for neighbour in [before, after] {
+			if neighbour != 0 && neighbour != n - 1 {
+				versions[neighbour] += 1;
+				let area = area(neighbour, &prev, &next);
+				heap.push(Reverse((area.to_bits(), neighbour, versions[neighbour])));
+			}
+		}
+	}
+	keep
+}
+
+fn triangle_area<T: Coord>(a: &T, b: &T, c: &T) -> f32 {
+	((b.x() - a.x()) * (c.y() - a.y()) - (c.x() - a.x()) * (b.y() - a.y())).abs() /
+		2.0
+}
+
+/// The distance of `p` to the closest point on the segment from `a` to `b`
+pub fn segment_dist<T: Coord>(p: &T, a: &T, b: &T) -> f32 {
+	let (dx, dy) = (b.x() - a.x(), b.y() - a.y());
+	let sq_len = dx * dx + dy * dy;
+	let t = if sq_len > 0.0 {
+		(((p.x() - a.x()) * dx + (p.y() - a.y()) * dy) / sq_len).clamp(0.0, 1.0)
+	} else {
+		0.0
+	};
+	let (ox, oy) = (a.x() + t * dx - p.x(), a.y() + t * dy - p.y());
+	(ox * ox + oy * oy).sqrt()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::coord::Point;
+	use crate::random::Random;
+
+	const P: fn(x: f32, y: f32) -> Point = Point::new;
+	const METHODS: [SimplifyMethod; 2] = [
+		SimplifyMethod::RamerDouglasPeucker,
+		SimplifyMethod::VisvalingamWhyatt,
+	];
+
+	/// The largest distance of a point on one polyline to the other polyline,
+	/// approximated by sampling both of them densely
+	fn hausdorff(a: &[Point], b: &[Point]) -> f32 {
+		let one_sided = |from: &[Point], to: &[Point]| {
+			let samples = from.windows(2).flat_map(|w| {
+				(0..=10).map(move |i| {
+					let t = i as f32 / 10.0;
+					P(
+						w[0].x() + t * (w[1].x() - w[0].x()),
+						w[0].y() + t * (w[1].y() - w[0].y()),
+					)
+				})
+			});
+			samples
+				.map(|p| {
+					to.windows(2)
+						.map(|w| segment_dist(&p, &w[0], &w[1]))
+						.fold(f32::MAX, f32::min)
+				})
+				.fold(0.0, f32::max)
+		};
+		one_sided(a, b).max(one_sided(b, a))
+	}
+
+	#[test]
+	fn straight_line() {
+		let line: Vec<Point> = (0..10).map(|x| P(x as f32, 0.0)).collect();
+		for method in METHODS {
+			let simplified = simplify_polyline(&line, 0.1, method);
+			assert_eq!(simplified, vec![P(0.0, 0.0), P(9.0, 0.0)]);
+		}
+	}
+
+	#[test]
+	fn corner() {
+		// A staircase turning into a vertical line keeps its corner
+		let line = [
+			P(0.0, 0.0),
+			P(1.0, 0.0),
+			P(2.0, 1.0),
+			P(3.0, 1.0),
+			P(4.0, 2.0),
+			P(4.0, 3.0),
+			P(4.0, 4.0),
+			P(4.0, 5.0),
+		];
+		for method in METHODS {
+			let simplified = simplify_polyline(&line, 0.8, method);
+			assert_eq!(simplified, vec![P(0.0, 0.0), P(4.0, 2.0), P(4.0, 5.0)]);
+		}
+	}
+
+	#[test]
+	fn within_tolerance() {
+		let mut random = Random::new(11);
+		let mut noise = || random.next_f64() as f32 * 4.0 - 2.0;
+		let line: Vec<Point> = (0..200)
+			.map(|i| {
+				let t = i as f32 / 10.0;
+				P(t * 10.0 + noise(), t.sin() * 30.0 + noise())
+			})
+			.collect();
+		for method in METHODS {
+			for tolerance in [0.5, 2.0, 5.0] {
+				let simplified = simplify_polyline(&line, tolerance, method);
+				assert!(simplified.len() < line.len());
+				assert_eq!(simplified.first(), line.first());
+				assert_eq!(simplified.last(), line.last());
+				let deviation = hausdorff(&line, &simplified);
+				assert!(
+					deviation <= tolerance + 1e-3,
+					"{:?} deviates by {} at a tolerance of {}",
+					method,
+					deviation,
+					tolerance
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn removed_vertices() {
+		let mut strokes = Strokes::new();
+		strokes.push((0..5).map(|x| P(x as f32, 0.0)));
+		strokes.push([P(0.0, 5.0)]);
+		strokes.push([P(0.0, 10.0), P(5.0, 10.0)]);
+		for method in METHODS {
+			let (simplified, removed) = simplify(&strokes, 0.5, method);
+			assert_eq!(removed, 3);
+			assert_eq!(simplified.offsets(), &[0, 2, 3]);
+		}
+	}
+}
@@ -0,0 +1,175 @@
+use wasm_bindgen::prelude::*;
+
+use super::coord::{Coord, Point};
+use super::stroke::Strokes;
+
+/// How strokes are smoothed before they are drawn
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Smoothing {
+	None = 0,
+	/// Cut every corner, replacing it with two points at a quarter and three
+	/// quarters of its segments. Each iteration doubles the number of points.
+	Chaikin = 1,
+	/// Move every point halfway towards the middle of its neighbours, once per
+	/// iteration
+	Laplacian = 2,
+	/// Replace every point with the average of the points around it
+	MovingAverage = 3,
+}
+
+/// Smooth every stroke with the given method. `strength` is the number of
+/// iterations for `Chaikin` and `Laplacian`, and the number of points taken
+/// on either side for `MovingAverage`. The first and last point of every
+/// stroke stay in place.
+pub fn smooth<T: Coord>(
+	strokes: &Strokes<T>,
+	smoothing: Smoothing,
+	strength: u32,
+) -> Strokes<Point> {
+	let mut smoothed = Strokes::new();
+	for stroke in strokes.iter() {
+		let points = stroke.iter().map(|p| Point::new(p.x(), p.y())).collect();
+		let strength = strength as usize;
+		smoothed.push(match smoothing {
+			Smoothing::None => points,
+			Smoothing::Chaikin => chaikin(points, strength),
+			Smoothing::Laplacian => laplacian(points, strength),
+			Smoothing::MovingAverage => moving_average(&points, strength),
+		});
+	}
+	smoothed
+}
+
+pub fn chaikin(mut points: Vec<Point>, iterations: usize) -> Vec<Point> {
+	for _ in 0..iterations {
+		if points.len() < 3 {
+			break;
+		}
+		let mut cut = Vec::with_capacity(points.len() * 2);
+		cut.push(points[0]);
+		for pair in points.windows(2) {
+			cut.push(lerp(&pair[0], &pair[1], 0.25));
+			cut.push(lerp(&pair[0], &pair[1], 0.75));
+		}
+		cut.push(points[points.len() - 1]);
+		// The cuts next to the ends would only shorten the first and last
+		// segment without rounding anything
+		cut.remove(1);
+		cut.remove(cut.len() - 2);
+		points = cut;
+	}
+	points
+}
+
+pub fn laplacian(mut points: Vec<Point>, iterations: usize) -> Vec<Point> {
+	if points.len() < 3 {
+		return points;
+	}
+	for _ in 0..iterations {
+		let previous = points.clone();
+		for (i, window) in previous.windows(3).enumerate() {
+			let middle = lerp(&window[0], &window[2], 0.5);
+			points[i + 1] = lerp(&window[1], &middle, 0.5);
+		}
+	}
+	points
+}
+
+/// Average each point with up to `radius` points on either side. Close to the
+/// ends, the window shrinks to the same number of points on both sides, so
+/// that the ends stay in place and the stroke isn't pulled inwards.
+pub fn moving_average(points: &[Point], radius: usize) -> Vec<Point> {
+	let last = points.len().saturating_sub(1);
+	(0..points.len())
+		.map(|i| {
+			let radius = radius.min(i).min(last - i);
+			let window = &points[i - radius..=i + radius];
+			let n = window.len() as f32;
+			let x = window.iter().map(|p| p.x()).sum::<f32>() / n;
+			let y = window.iter().map(|p| p.y()).sum::<f32>() / n;
+			Point::new(x, y)
+		})
+		.collect()
+}
+
+fn lerp(a: &Point, b: &Point, t: f32) -> Point {
+	Point::new(a.x() + (b.x() - a.x()) * t, a.y() + (b.y() - a.y()) * t)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const P: fn(x: f32, y: f32) -> Point = Point::new;
+
+	fn corner() -> Vec<Point> {
+		vec![P(0.0, 0.0), P(4.0, 0.0), P(4.0, 4.0)]
+	}
+
+	#[test]
+	fn chaikin_cuts_corners() {
+		assert_eq!(
+			chaikin(corner(), 1),
+			vec![P(0.0, 0.0), P(3.0, 0.0), P(4.0, 1.0), P(4.0, 4.0)]
+		);
+		let smoothed = chaikin(corner(), 3);
+		assert_eq!(smoothed.len(), 10);
+		assert_eq!(smoothed.first(), Some(&P(0.0, 0.0)));
+		assert_eq!(smoothed.last(), Some(&P(4.0, 4.0)));
+		// Straight lines stay straight
+		let line = vec![P(0.0, 0.0), P(1.0, 1.0), P(2.0, 2.0), P(3.0, 3.0)];
+		assert!(chaikin(line, 2).iter().all(|p| p.x() == p.y()));
+	}
+
+	#[test]
+	fn laplacian_keeps_ends() {
+		assert_eq!(
+			laplacian(corner(), 1),
+			vec![P(0.0, 0.0), P(3.0, 1.0), P(4.0, 4.0)]
+		);
+		let zigzag: Vec<Point> = (0..9)
+			.map(|x| P(x as f32, if x % 2 == 0 { 0.0 } else { 2.0 }))
+			.collect();
+		let smoothed = laplacian(zigzag.clone(), 4);
+		assert_eq!(smoothed[0], zigzag[0]);
+		assert_eq!(smoothed[8], zigzag[8]);
+		// The zigzag flattens out
+		assert!(smoothed[3..6].iter().all(|p| (p.y() - 1.0).abs() < 0.1));
+	}
+
+	#[test]
+	fn moving_average_window() {
+		let points = [
+			P(0.0, 0.0),
+			P(1.0, 3.0),
+			P(2.0, 0.0),
+			P(3.0, 3.0),
+			P(4.0, 0.0),
+		];
+		assert_eq!(
+			moving_average(&points, 2),
+			vec![
+				P(0.0, 0.0),
+				P(1.0, 1.0),
+				P(2.0, 1.2),
+				P(3.0, 1.0),
+				P(4.0, 0.0)
+			]
+		);
+		assert_eq!(moving_average(&points, 0), points);
+	}
+
+	#[test]
+	fn smooth_strokes() {
+		let mut strokes = Strokes::new();
+		strokes.push(corner());
+		strokes.push([P(9.0, 9.0)]);
+		strokes.push([P(0.0, 9.0), P(5.0, 9.0)]);
+		let smoothed = smooth(&strokes, Smoothing::Chaikin, 1);
+		assert_eq!(smoothed.offsets(), &[0, 4, 5]);
+		assert_eq!(smoothed.get(1), &[P(9.0, 9.0)]);
+		assert_eq!(smoothed.get(2), &[P(0.0, 9.0), P(5.0, 9.0)]);
+		assert_eq!(smooth(&strokes, Smoothing::None, 3), strokes);
+	}
+}
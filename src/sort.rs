@@ -0,0 +1,503 @@
+use std::collections::VecDeque;
+
+use wasm_bindgen::prelude::*;
+
+use super::coord::{sq_dist, Coord};
+use super::mst::{mst_order, mst_strokes, MstSort};
+use super::quadtree::{QuadTree, Rectangle, DEFAULT_CAPACITY};
+use super::stroke::Strokes;
+
+/// The algorithm used to order the edge points
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortMode {
+	/// Grow the path from the first point, always appending the nearest
+	/// remaining neighbour of the last point
+	Greedy = 0,
+	/// Grow the path from both of its ends, always extending the end that has
+	/// the closer nearest neighbour
+	Bidirectional = 1,
+	/// Walk a minimum spanning tree of the points depth first, turning every
+	/// branch of the tree into a separate stroke
+	Mst = 2,
+}
+
+pub fn sort_points<T: Coord>(
+	points: Vec<T>,
+	w: f32,
+	h: f32,
+	initial_search_radius: f32,
+	mode: SortMode,
+) -> Vec<T> {
+	match mode {
+		SortMode::Greedy => {
+			sort_by_distance_2d(points, w, h, initial_search_radius)
+		}
+		SortMode::Bidirectional => sort_bidirectional(points, w, h),
+		SortMode::Mst => mst_order(points, w, h, initial_search_radius),
+	}
+}
+
+/// Order the points and split them into strokes. The path based modes always
+/// produce a single stroke, while `SortMode::Mst` produces one per branch.
+pub fn sort_strokes<T: Coord>(
+	points: Vec<T>,
+	w: f32,
+	h: f32,
+	initial_search_radius: f32,
+	mode: SortMode,
+) -> Strokes<T> {
+	match mode {
+		SortMode::Mst => mst_strokes(points, w, h, initial_search_radius),
+		_ => {
+			Strokes::single(sort_points(points, w, h, initial_search_radius, mode))
+		}
+	}
+}
+
+pub fn sort_by_distance_2d<T: Coord>(
+	points: Vec<T>,
+	w: f32,
+	h: f32,
+	initial_search_radius: f32,
+) -> Vec<T> {
+	if points.len() <= 2 {
+		return points;
+	}
+	let mut state =
+		IncrementalSort::new(points, w, h, initial_search_radius, SortMode::Greedy);
+	state.finish();
+	state.into_sorted()
+}
+
+/// Order the points by growing a chain from both of its ends. In every step
+/// the nearest remaining neighbours of the head and of the tail are looked up,
+/// and whichever is closer gets attached to its end. Compared to growing the
+/// chain from one end only, this leaves fewer isolated points behind, that
+/// would otherwise have to be connected by long jumps at the end of the path.
+pub fn sort_bidirectional<T: Coord>(points: Vec<T>, w: f32, h: f32) -> Vec<T> {
+	if points.len() <= 2 {
+		return points;
+	}
+	let mut state =
+		IncrementalSort::new(points, w, h, 0.0, SortMode::Bidirectional);
+	state.finish();
+	state.into_sorted()
+}
+
+/// A sort, that can be advanced a limited number of points at a time, so that
+/// it can be spread over multiple frames or abandoned halfway through.
+///
+/// The path based modes place one point per step. `SortMode::Mst` takes
+/// steps of similar cost: each one queries the neighbours of a point, adds an
+/// edge to the tree or visits a node while walking it.
+pub struct IncrementalSort<T: Coord> {
+	mode: SortMode,
+	initial_search_radius: f32,
+	qt: QuadTree<T>,
+	chain: VecDeque<T>,
+	// The nearest neighbours of the ends are cached in bidirectional mode,
+	// because only the end that was extended, or whose neighbour was taken by
+	// the other end, changes
+	head_next: Option<T>,
+	tail_next: Option<T>,
+	mst: Option<MstSort<T>>,
+}
+
+impl<T: Coord> IncrementalSort<T> {
+	pub fn new(
+		points: Vec<T>,
+		w: f32,
+		h: f32,
+		initial_search_radius: f32,
+		mode: SortMode,
+	) -> Self {
+		let mut state =
+			Self::empty(w, h, initial_search_radius, mode, DEFAULT_CAPACITY);
+		state.restart(points);
+		state
+	}
+
+	/// Create a sort without any points. Use `restart` to fill it.
+	pub fn empty(
+		w: f32,
+		h: f32,
+		initial_search_radius: f32,
+		mode: SortMode,
+		capacity: usize,
+	) -> Self {
+		let boundary = Rectangle::new(w / 2.0, h / 2.0, w, h);
+		let mst = match mode {
+			SortMode::Mst => {
+				Some(MstSort::empty(w, h, initial_search_radius, capacity))
+			}
+			_ => None,
+		};
+		Self {
+			mode,
+			initial_search_radius,
+			qt: QuadTree::with_capacity(boundary, capacity),
+			chain: VecDeque::new(),
+			head_next: None,
+			tail_next: None,
+			mst,
+		}
+	}
+
+	/// Throw away the current progress and start sorting a new set of points.
+	/// The buffers of the previous sort are reused.
+	pub fn restart<I: IntoIterator<Item = T>>(&mut self, points: I) {
+		self.qt.clear();
+		self.chain.clear();
+		self.head_next = None;
+		self.tail_next = None;
+		if let Some(mst) = &mut self.mst {
+			mst.restart(points);
+			return;
+		}
+
+		// Start with the first point inside of the frame
+		let mut points = points.into_iter();
+		let start = match points.find(|&p| self.qt.insert(p)) {
+			Some(start) => start,
+			None => return,
+		};
+		for p in points {
+			self.qt.insert(p);
+		}
+		self.qt.remove(&start);
+		self.chain.push_back(start);
+		if self.mode == SortMode::Bidirectional {
+			self.head_next = self.qt.nearest(start.x(), start.y());
+			self.tail_next = self.head_next;
+		}
+	}
+
+	/// Take up to `budget` more steps. Returns `true` once all points are
+	/// sorted.
+	pub fn step(&mut self, budget: usize) -> bool {
+		if let Some(mst) = &mut self.mst {
+			return mst.step(budget);
+		}
+		for _ in 0..budget {
+			let placed = match self.mode {
+				SortMode::Bidirectional => self.extend_closer_end(),
+				_ => self.extend_tail(),
+			};
+			if !placed {
+				return true;
+			}
+		}
+		self.is_done()
+	}
+
+	/// Sort all remaining points
+	pub fn finish(&mut self) {
+		self.step(usize::MAX);
+	}
+
+	pub fn is_done(&self) -> bool {
+		match &self.mst {
+			Some(mst) => mst.is_done(),
+			None => self.qt.size() == 0,
+		}
+	}
+
+	/// Number of points, that still need to be placed
+	pub fn remaining(&self) -> usize {
+		match &self.mst {
+			Some(mst) => mst.remaining(),
+			None => self.qt.size(),
+		}
+	}
+
+	/// The points placed so far, in order
+	pub fn sorted(&self) -> impl Iterator<Item = &T> + '_ {
+		let mst = self.mst.iter().flat_map(|mst| mst.order());
+		mst.chain(self.chain.iter())
+	}
+
+	pub fn into_sorted(self) -> Vec<T> {
+		match self.mst {
+			Some(mst) => mst.into_order(),
+			None => self.chain.into(),
+		}
+	}
+
+	fn extend_tail(&mut self) -> bool {
+		let tail = match self.chain.back() {
+			Some(&tail) if self.qt.size() > 0 => tail,
+			_ => return false,
+		};
+		let mut search_radius = self.initial_search_radius;
+		let found = loop {
+			let search_range =
+				Rectangle::new(tail.x(), tail.y(), search_radius, search_radius);
+			let r = self.qt.query(&search_range);
+			search_radius *= 2.0;
+			if !r.is_empty() {
+				break r;
+			}
+		};
+		let mut closest: T = found[0];
+		let mut closest_dist = f64::INFINITY;
+		for element in found {
+			let d = sq_dist(&tail, &element);
+			if d < closest_dist {
+				closest_dist = d;
+				closest = element;
+			}
+		}
+		self.qt.remove(&closest);
+		self.chain.push_back(closest);
+		true
+	}
+
+	fn extend_closer_end(&mut self) -> bool {
+		let (h_next, t_next) = match (self.head_next, self.tail_next) {
+			(Some(h_next), Some(t_next)) => (h_next, t_next),
+			_ => return false,
+		};
+		let head = self.chain[0];
+		let tail = self.chain[self.chain.len() - 1];
+		if sq_dist(&head, &h_next) <= sq_dist(&tail, &t_next) {
+			self.qt.remove(&h_next);
+			self.chain.push_front(h_next);
+			self.head_next = self.qt.nearest(h_next.x(), h_next.y());
+			if same_position(&h_next, &t_next) {
+				self.tail_next = self.qt.nearest(tail.x(), tail.y());
+			}
+		} else {
+			self.qt.remove(&t_next);
+			self.chain.push_back(t_next);
+			self.tail_next = self.qt.nearest(t_next.x(), t_next.y());
+			if same_position(&t_next, &h_next) {
+				self.head_next = self.qt.nearest(head.x(), head.y());
+			}
+		}
+		true
+	}
+}
+
+fn same_position<T: Coord>(a: &T, b: &T) -> bool {
+	a.x() == b.x() && a.y() == b.y()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{BitPoint, Point};
+
+	const BIT_POINTS: &str = include_str!("./points.txt");
+
+	fn load_points() -> (Vec<Point>, f32, f32) {
+		let points: Vec<Point> = BIT_POINTS
+			.split_whitespace()
+			.map(|bp_str| {
+				bp_str
+					.parse::<u32>()
+					.expect("All of the contents of points.txt are numbers")
+			})
+			.map(BitPoint::from_raw)
+			.map(Point::from)
+			.collect();
+		let w = points.iter().fold(0.0, |max: f32, p| max.max(p.x())) + 1.0;
+		let h = points.iter().fold(0.0, |max: f32, p| max.max(p.y())) + 1.0;
+		(points, w, h)
+	}
+
+	fn path_length(points: &[Point]) -> f64 {
+		points
+			.windows(2)
+			.map(|pair| sq_dist(&pair[0], &pair[1]).sqrt())
+			.sum()
+	}
+
+	fn count_jumps(points: &[Point], threshold: f64) -> usize {
+		let sq_threshold = threshold * threshold;
+		points
+			.windows(2)
+			.filter(|pair| sq_dist(&pair[0], &pair[1]) > sq_threshold)
+			.count()
+	}
+
+	#[test]
+	fn basic() {
+		let sorted = sort_by_distance_2d(
+			vec![
+				Point::new(0.0, 0.0),
+				Point::new(3.0, 3.0),
+				Point::new(1.0, 1.0),
+				Point::new(4.0, 4.0),
+				Point::new(2.0, 2.0),
+			],
+			6.0,
+			6.0,
+			2.0,
+		);
+		let ordered = vec![
+			Point::new(0.0, 0.0),
+			Point::new(1.0, 1.0),
+			Point::new(2.0, 2.0),
+			Point::new(3.0, 3.0),
+			Point::new(4.0, 4.0),
+		];
+		assert_eq!(sorted, ordered);
+	}
+
+	#[test]
+	fn bidirectional_basic() {
+		let sorted = sort_bidirectional(
+			vec![
+				Point::new(2.0, 2.0),
+				Point::new(0.0, 0.0),
+				Point::new(3.5, 3.5),
+				Point::new(1.0, 1.0),
+				Point::new(5.0, 5.0),
+			],
+			6.0,
+			6.0,
+		);
+		let ordered = vec![
+			Point::new(0.0, 0.0),
+			Point::new(1.0, 1.0),
+			Point::new(2.0, 2.0),
+			Point::new(3.5, 3.5),
+			Point::new(5.0, 5.0),
+		];
+		assert_eq!(sorted, ordered);
+	}
+
+	#[test]
+	fn bidirectional_huge() {
+		let (points, w, h) = load_points();
+		let greedy = sort_points(points.clone(), w, h, 2.0, SortMode::Greedy);
+		let bidirectional =
+			sort_points(points.clone(), w, h, 2.0, SortMode::Bidirectional);
+		assert_eq!(bidirectional.len(), greedy.len());
+
+		let greedy_length = path_length(&greedy);
+		let bidirectional_length = path_length(&bidirectional);
+		let greedy_jumps = count_jumps(&greedy, 10.0);
+		let bidirectional_jumps = count_jumps(&bidirectional, 10.0);
+		assert!(
+			bidirectional_length < greedy_length,
+			"bidirectional: {}, greedy: {}",
+			bidirectional_length,
+			greedy_length
+		);
+		assert!(
+			bidirectional_jumps < greedy_jumps,
+			"bidirectional: {}, greedy: {}",
+			bidirectional_jumps,
+			greedy_jumps
+		);
+	}
+
+	#[test]
+	fn incremental() {
+		let (points, w, h) = load_points();
+		for mode in [SortMode::Greedy, SortMode::Bidirectional, SortMode::Mst] {
+			let complete = sort_points(points.clone(), w, h, 2.0, mode);
+			let mut state = IncrementalSort::new(points.clone(), w, h, 2.0, mode);
+			let mut steps = 0;
+			while !state.step(1000) {
+				steps += 1;
+				assert!(state.sorted().count() <= complete.len());
+			}
+			assert!(state.is_done());
+			assert_eq!(state.remaining(), 0);
+			assert!(steps > 0, "{:?}", mode);
+			assert_eq!(state.into_sorted(), complete, "{:?}", mode);
+		}
+	}
+
+	#[test]
+	fn incremental_partial() {
+		let points = vec![
+			Point::new(0.0, 0.0),
+			Point::new(3.0, 3.0),
+			Point::new(1.0, 1.0),
+			Point::new(4.0, 4.0),
+			Point::new(2.0, 2.0),
+		];
+		let mut state =
+			IncrementalSort::new(points, 6.0, 6.0, 2.0, SortMode::Greedy);
+		assert!(!state.step(2));
+		assert_eq!(state.remaining(), 2);
+		let sorted: Vec<Point> = state.sorted().copied().collect();
+		assert_eq!(
+			sorted,
+			vec![
+				Point::new(0.0, 0.0),
+				Point::new(1.0, 1.0),
+				Point::new(2.0, 2.0)
+			]
+		);
+		assert!(state.step(2));
+		assert_eq!(state.sorted().count(), 5);
+	}
+
+	#[test]
+	fn mst_budget() {
+		let (points, w, h) = load_points();
+		let num_points = points.len();
+		let mut state = IncrementalSort::new(points, w, h, 2.0, SortMode::Mst);
+		// Not even the neighbour graph is done after a few steps
+		assert!(!state.step(10));
+		assert_eq!(state.sorted().count(), 0);
+		assert_eq!(state.remaining(), num_points);
+		let mut steps = 1;
+		while !state.step(1000) {
+			steps += 1;
+		}
+		// Every point is queried, connected and visited a few times
+		assert!(steps > num_points * 3 / 1000, "{}", steps);
+		assert_eq!(state.sorted().count(), num_points);
+	}
+
+	#[test]
+	fn restart() {
+		let (points, w, h) = load_points();
+		let mut state =
+			IncrementalSort::empty(w, h, 2.0, SortMode::Greedy, DEFAULT_CAPACITY);
+		state.restart(points.iter().copied());
+		assert!(!state.step(100));
+		state.restart(points.iter().copied());
+		state.finish();
+		let sorted: Vec<Point> = state.sorted().copied().collect();
+		assert_eq!(sorted, sort_by_distance_2d(points, w, h, 2.0));
+	}
+
+	#[test]
+	fn start_outside_of_frame() {
+		let points = vec![
+			Point::new(8.0, 1.0),
+			Point::new(0.0, 0.0),
+			Point::new(2.0, 2.0),
+			Point::new(1.0, 1.0),
+		];
+		for mode in [SortMode::Greedy, SortMode::Bidirectional, SortMode::Mst] {
+			let mut state = IncrementalSort::new(points.clone(), 4.0, 4.0, 2.0, mode);
+			state.finish();
+			let sorted: Vec<Point> = state.sorted().copied().collect();
+			assert_eq!(sorted.len(), 3, "{:?}", mode);
+			assert!(!sorted.contains(&Point::new(8.0, 1.0)), "{:?}", mode);
+		}
+	}
+
+	#[test]
+	fn mst_huge() {
+		let (points, w, h) = load_points();
+		let num_points = points.len();
+		let order = sort_points(points.clone(), w, h, 2.0, SortMode::Mst);
+		assert_eq!(order.len(), num_points);
+
+		let strokes = sort_strokes(points, w, h, 2.0, SortMode::Mst);
+		assert!(strokes.len() > 1);
+		// Every stroke, but the first one of each tree, repeats the point it
+		// branches off from
+		assert!(strokes.points().len() >= num_points);
+		assert!(strokes.points().len() < num_points + strokes.len());
+	}
+}
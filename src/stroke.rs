@@ -0,0 +1,163 @@
+use super::coord::{sq_dist, Coord};
+
+/// A list of polylines, stored as one flat list of points and the index of
+/// the point each stroke starts at
+#[derive(Clone, Debug, PartialEq)]
+pub struct Strokes<T: Coord> {
+	points: Vec<T>,
+	offsets: Vec<usize>,
+}
+
+impl<T: Coord> Strokes<T> {
+	pub fn new() -> Self {
+		Self {
+			points: Vec::new(),
+			offsets: Vec::new(),
+		}
+	}
+
+	/// Create a list, that contains all points in a single stroke
+	pub fn single(points: Vec<T>) -> Self {
+		let offsets = if points.is_empty() { vec![] } else { vec![0] };
+		Self { points, offsets }
+	}
+
+	/// Create a list from a flat list of points and the stroke offsets.
+	/// Offsets that are out of order or out of bounds are dropped, as are
+	/// offsets that would create empty strokes.
+	pub fn from_parts(points: Vec<T>, offsets: &[usize]) -> Self {
+		let mut strokes = Self {
+			points,
+			offsets: Vec::with_capacity(offsets.len()),
+		};
+		for &offset in offsets {
+			let is_after_last =
+				strokes.offsets.last().is_none_or(|&last| offset > last);
+			if is_after_last && offset < strokes.points.len() {
+				strokes.offsets.push(offset);
+			}
+		}
+		if strokes.offsets.first() != Some(&0) && !strokes.points.is_empty() {
+			strokes.offsets.insert(0, 0);
+		}
+		strokes
+	}
+
+	/// Append a stroke. Empty strokes are ignored.
+	pub fn push<I: IntoIterator<Item = T>>(&mut self, stroke: I) {
+		let offset = self.points.len();
+		self.points.extend(stroke);
+		if self.points.len() > offset {
+			self.offsets.push(offset);
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.offsets.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.offsets.is_empty()
+	}
+
+	pub fn get(&self, i: usize) -> &[T] {
+		let end = self
+			.offsets
+			.get(i + 1)
+			.copied()
+			.unwrap_or(self.points.len());
+		&self.points[self.offsets[i]..end]
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = &[T]> + '_ {
+		(0..self.len()).map(move |i| self.get(i))
+	}
+
+	pub fn points(&self) -> &[T] {
+		&self.points
+	}
+
+	pub fn offsets(&self) -> &[usize] {
+		&self.offsets
+	}
+
+	/// Split the strokes wherever two consecutive points are at least
+	/// `max_distance` apart, so that long jumps aren't drawn
+	pub fn split_at_jumps(&self, max_distance: f32) -> Self {
+		let sq_max_distance = (max_distance * max_distance) as f64;
+		let mut offsets = Vec::with_capacity(self.offsets.len());
+		for (stroke, &offset) in self.iter().zip(&self.offsets) {
+			offsets.push(offset);
+			for (i, pair) in stroke.windows(2).enumerate() {
+				if sq_dist(&pair[0], &pair[1]) >= sq_max_distance {
+					offsets.push(offset + i + 1);
+				}
+			}
+		}
+		Self {
+			points: self.points.clone(),
+			offsets,
+		}
+	}
+
+	pub fn into_parts(self) -> (Vec<T>, Vec<usize>) {
+		(self.points, self.offsets)
+	}
+}
+
+impl<T: Coord> Default for Strokes<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::Point;
+
+	const P: fn(x: f32, y: f32) -> Point = Point::new;
+
+	#[test]
+	fn push_and_iter() {
+		let mut strokes = Strokes::new();
+		strokes.push(vec![P(0.0, 0.0), P(1.0, 0.0)]);
+		strokes.push(vec![]);
+		strokes.push(vec![P(5.0, 5.0)]);
+		assert_eq!(strokes.len(), 2);
+		assert_eq!(strokes.offsets(), &[0, 2]);
+		let collected: Vec<&[Point]> = strokes.iter().collect();
+		assert_eq!(
+			collected,
+			vec![&[P(0.0, 0.0), P(1.0, 0.0)][..], &[P(5.0, 5.0)][..]]
+		);
+	}
+
+	#[test]
+	fn from_parts() {
+		let points = vec![P(0.0, 0.0), P(1.0, 0.0), P(2.0, 0.0), P(3.0, 0.0)];
+		let strokes = Strokes::from_parts(points.clone(), &[2, 2, 1, 9]);
+		assert_eq!(strokes.offsets(), &[0, 2]);
+		let strokes = Strokes::from_parts(points, &[]);
+		assert_eq!(strokes.offsets(), &[0]);
+		let strokes = Strokes::<Point>::from_parts(vec![], &[0]);
+		assert!(strokes.is_empty());
+	}
+
+	#[test]
+	fn split_at_jumps() {
+		let points = vec![
+			P(0.0, 0.0),
+			P(1.0, 0.0),
+			P(5.0, 0.0),
+			P(6.0, 0.0),
+			P(6.0, 1.0),
+			P(20.0, 1.0),
+		];
+		let strokes = Strokes::from_parts(points, &[0, 4]);
+		let split = strokes.split_at_jumps(4.0);
+		assert_eq!(split.offsets(), &[0, 2, 4, 5]);
+		assert_eq!(split.points(), strokes.points());
+		assert_eq!(strokes.split_at_jumps(100.0), strokes);
+	}
+}
@@ -0,0 +1,132 @@
+use wasm_bindgen::prelude::*;
+
+use super::coord::{Coord, Point};
+use super::curve::{sample_curve, Parametrization};
+use super::pipes::{route_pipes, PipeRouting};
+use super::smooth::{smooth, Smoothing};
+use super::stroke::Strokes;
+use super::width::WidthSource;
+
+/// Curves are traced with lines, that are never farther than this from them,
+/// when they can't be drawn as Bézier curves
+const CURVE_TOLERANCE: f32 = 0.1;
+
+/// How the sorted points are drawn
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrawingMode {
+	/// A dot for every point
+	Points = 0,
+	/// A Catmull-Rom spline through the points
+	Curve = 1,
+	/// Horizontal and vertical lines, with an elbow between every two points
+	Pipes = 2,
+	/// Straight lines between the points
+	Lines = 3,
+}
+
+/// The look of a drawing, like the settings of the app's canvas
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrawStyle {
+	pub mode: DrawingMode,
+	/// Size of the drawing, in the units of the points
+	pub width: f32,
+	pub height: f32,
+	pub stroke_width: f32,
+	/// What the width of the lines depends on. Unless it is constant, lines
+	/// range from `min_stroke_width` to `stroke_width`.
+	pub width_source: WidthSource,
+	pub min_stroke_width: f32,
+	/// Distance from the ends of a stroke, at which tapered lines reach their
+	/// full width
+	pub taper_length: f32,
+	/// Opacity of the lines, from 0 to 1
+	pub stroke_opacity: f32,
+	/// Opacity of the background, from 0 to 1
+	pub background_opacity: f32,
+	/// Lines are interrupted between points, that are at least this far apart
+	pub max_distance: f32,
+	/// How the knots of the curves are spaced
+	pub curve_parametrization: Parametrization,
+	/// How the elbows of the pipes are placed
+	pub pipe_routing: PipeRouting,
+	/// Seed of the hashed pipe routing
+	pub seed: u64,
+	/// How the parts of the strokes are smoothed before they are drawn. Dots
+	/// aren't smoothed.
+	pub smoothing: Smoothing,
+	/// Iterations or window radius of the smoothing
+	pub smoothing_strength: u32,
+	/// Draw white lines on black, instead of black lines on white
+	pub invert_colors: bool,
+}
+
+#[wasm_bindgen]
+impl DrawStyle {
+	#[wasm_bindgen(constructor)]
+	pub fn new(width: f32, height: f32) -> DrawStyle {
+		Self {
+			mode: DrawingMode::Lines,
+			width,
+			height,
+			stroke_width: 1.0,
+			width_source: WidthSource::Constant,
+			min_stroke_width: 0.25,
+			taper_length: 20.0,
+			stroke_opacity: 1.0,
+			background_opacity: 1.0,
+			max_distance: 80.0,
+			curve_parametrization: Parametrization::Uniform,
+			pipe_routing: PipeRouting::HorizontalFirst,
+			seed: 0,
+			smoothing: Smoothing::None,
+			smoothing_strength: 2,
+			invert_colors: false,
+		}
+	}
+}
+
+impl DrawStyle {
+	/// Split the strokes at jumps of `max_distance` or more, and smooth the
+	/// parts, unless they are drawn as dots. Each part is drawn as one path.
+	pub fn parts<T: Coord>(&self, strokes: &Strokes<T>) -> Strokes<Point> {
+		let smoothing = match self.mode {
+			DrawingMode::Points => Smoothing::None,
+			_ => self.smoothing,
+		};
+		smooth(
+			&strokes.split_at_jumps(self.max_distance),
+			smoothing,
+			self.smoothing_strength,
+		)
+	}
+
+	/// The lines drawn for a part: a single point for every dot, or the
+	/// lines, pipes or traced curve through the part. Parts, that are too
+	/// short to be drawn in the mode, give none.
+	pub fn shapes(&self, part: &[Point]) -> Vec<Vec<Point>> {
+		let line = match self.mode {
+			DrawingMode::Points => return part.iter().map(|&p| vec![p]).collect(),
+			DrawingMode::Lines => part.to_vec(),
+			DrawingMode::Pipes => route_pipes(part, self.pipe_routing, self.seed),
+			DrawingMode::Curve => {
+				sample_curve(part, self.curve_parametrization, CURVE_TOLERANCE)
+			}
+		};
+		if line.len() < 2 {
+			vec![]
+		} else {
+			vec![line]
+		}
+	}
+
+	/// The gray values of the background and of the lines
+	pub fn colors(&self) -> (u8, u8) {
+		if self.invert_colors {
+			(0, 255)
+		} else {
+			(255, 0)
+		}
+	}
+}
@@ -0,0 +1,343 @@
+use std::fmt::Write;
+
+use super::coord::{Coord, Point};
+use super::curve::catmull_rom_to_bezier;
+use super::pipes::route_pipes;
+use super::stroke::Strokes;
+use super::style::{DrawStyle, DrawingMode};
+use super::width::{outline, StrengthMap, StrokeWidths, WidthSource};
+
+/// Draw the strokes as an SVG document in the given style. Strokes are split
+/// at jumps of `max_distance` or more, like in the app, and every part is
+/// smoothed and becomes one `<path>` element. Lines of varying width are
+/// filled outlines. The strength map is only needed for lines, whose width
+/// depends on the edge strength.
+pub fn to_svg<T: Coord>(
+	strokes: &Strokes<T>,
+	style: &DrawStyle,
+	strength: Option<&StrengthMap>,
+) -> String {
+	let (background, foreground) = style.colors();
+	let mut svg = String::new();
+	writeln!(
+		svg,
+		"<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+		 viewBox=\"0 0 {w} {h}\">",
+		w = num(style.width),
+		h = num(style.height),
+	)
+	.unwrap();
+	if style.background_opacity > 0.0 {
+		writeln!(
+			svg,
+			"<rect width=\"100%\" height=\"100%\" fill=\"{}\" fill-opacity=\"{}\"/>",
+			color(background),
+			num(style.background_opacity),
+		)
+		.unwrap();
+	}
+	let variable_width = style.width_source != WidthSource::Constant;
+	if variable_width {
+		writeln!(
+			svg,
+			"<g fill=\"{}\" fill-opacity=\"{}\" fill-rule=\"nonzero\">",
+			color(foreground),
+			num(style.stroke_opacity),
+		)
+		.unwrap();
+	} else {
+		writeln!(
+			svg,
+			"<g fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" \
+			 stroke-opacity=\"{}\" stroke-linecap=\"round\" \
+			 stroke-linejoin=\"round\">",
+			color(foreground),
+			num(style.stroke_width),
+			num(style.stroke_opacity),
+		)
+		.unwrap();
+	}
+	let parts = style.parts(strokes);
+	let widths = StrokeWidths::new(&parts, style, strength);
+	for stroke in parts.iter() {
+		let d = if variable_width {
+			outline_data(stroke, style, &widths)
+		} else {
+			path_data(stroke, style)
+		};
+		if !d.is_empty() {
+			writeln!(svg, "<path d=\"{}\"/>", d).unwrap();
+		}
+	}
+	svg.push_str("</g>\n</svg>\n");
+	svg
+}
+
+/// The outline of a single stroke in the `d` attribute syntax. Strokes, that
+/// are too short to be drawn in the mode, give an empty string.
+fn path_data<T: Coord>(stroke: &[T], style: &DrawStyle) -> String {
+	let mut d = String::new();
+	match style.mode {
+		DrawingMode::Points => {
+			// A line of zero length with round caps makes a dot
+			for p in stroke {
+				write!(d, "M{} {}h0", num(p.x()), num(p.y())).unwrap();
+			}
+		}
+		DrawingMode::Lines => polyline(&mut d, stroke),
+		DrawingMode::Pipes => {
+			polyline(&mut d, &route_pipes(stroke, style.pipe_routing, style.seed))
+		}
+		DrawingMode::Curve => {
+			let segments = catmull_rom_to_bezier(stroke, style.curve_parametrization);
+			if let Some(first) = segments.first() {
+				write!(d, "M{} {}", num(first.from.x()), num(first.from.y())).unwrap();
+			}
+			for s in segments {
+				write!(
+					d,
+					"C{} {} {} {} {} {}",
+					num(s.c1.x()),
+					num(s.c1.y()),
+					num(s.c2.x()),
+					num(s.c2.y()),
+					num(s.to.x()),
+					num(s.to.y()),
+				)
+				.unwrap();
+			}
+		}
+	}
+	d
+}
+
+/// The outlines of the lines of a single stroke, like `path_data`
+fn outline_data(
+	stroke: &[Point],
+	style: &DrawStyle,
+	widths: &StrokeWidths,
+) -> String {
+	let mut d = String::new();
+	for line in style.shapes(stroke) {
+		for (i, p) in outline(&line, &widths.widths(&line)).iter().enumerate() {
+			let command = if i == 0 { 'M' } else { 'L' };
+			write!(d, "{}{} {}", command, num(p.x()), num(p.y())).unwrap();
+		}
+		d.push('Z');
+	}
+	d
+}
+
+fn polyline<T: Coord>(d: &mut String, points: &[T]) {
+	if points.len() < 2 {
+		return;
+	}
+	for (i, p) in points.iter().enumerate() {
+		let command = if i == 0 { 'M' } else { 'L' };
+		write!(d, "{}{} {}", command, num(p.x()), num(p.y())).unwrap();
+	}
+}
+
+/// Format a number with at most two decimals and without trailing zeros
+fn num(value: f32) -> String {
+	// Adding 0 turns -0 into 0
+	format!("{}", (value * 100.0).round() / 100.0 + 0.0)
+}
+
+fn color(gray: u8) -> String {
+	format!("#{:02x}{:02x}{:02x}", gray, gray, gray)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::curve::Parametrization;
+	use crate::pipes::PipeRouting;
+	use crate::smooth::Smoothing;
+
+	const P: fn(x: f32, y: f32) -> Point = Point::new;
+
+	fn strokes() -> Strokes<Point> {
+		Strokes::from_parts(
+			vec![
+				P(0.0, 0.0),
+				P(2.0, 1.0),
+				P(4.0, 4.0),
+				P(6.0, 4.0),
+				P(40.0, 40.0),
+				P(42.0, 40.0),
+			],
+			&[0],
+		)
+	}
+
+	fn style(mode: DrawingMode) -> DrawStyle {
+		DrawStyle {
+			mode,
+			max_distance: 10.0,
+			..DrawStyle::new(50.0, 50.0)
+		}
+	}
+
+	/// The `d` attributes of all paths
+	fn paths(svg: &str) -> Vec<&str> {
+		svg
+			.lines()
+			.filter_map(|line| line.strip_prefix("<path d=\""))
+			.map(|line| line.trim_end_matches("\"/>"))
+			.collect()
+	}
+
+	#[test]
+	fn document() {
+		let svg = to_svg(&strokes(), &style(DrawingMode::Lines), None);
+		let expected = [
+			r#"<svg xmlns="http://www.w3.org/2000/svg" width="50" height="50" viewBox="0 0 50 50">"#,
+			r##"<rect width="100%" height="100%" fill="#ffffff" fill-opacity="1"/>"##,
+			r##"<g fill="none" stroke="#000000" stroke-width="1" stroke-opacity="1" stroke-linecap="round" stroke-linejoin="round">"##,
+			r#"<path d="M0 0L2 1L4 4L6 4"/>"#,
+			r#"<path d="M40 40L42 40"/>"#,
+			"</g>",
+			"</svg>",
+		];
+		assert_eq!(svg.lines().collect::<Vec<_>>(), expected);
+	}
+
+	#[test]
+	fn style_attributes() {
+		let style = DrawStyle {
+			stroke_width: 2.5,
+			stroke_opacity: 0.5,
+			background_opacity: 0.0,
+			invert_colors: true,
+			..style(DrawingMode::Lines)
+		};
+		let svg = to_svg(&strokes(), &style, None);
+		assert!(!svg.contains("<rect"));
+		assert!(svg.contains(
+			"stroke=\"#ffffff\" stroke-width=\"2.5\" stroke-opacity=\"0.5\""
+		));
+	}
+
+	#[test]
+	fn max_distance() {
+		let style = DrawStyle {
+			max_distance: 100.0,
+			..style(DrawingMode::Lines)
+		};
+		let svg = to_svg(&strokes(), &style, None);
+		assert_eq!(paths(&svg), vec!["M0 0L2 1L4 4L6 4L40 40L42 40"]);
+	}
+
+	#[test]
+	fn points() {
+		let svg = to_svg(&strokes(), &style(DrawingMode::Points), None);
+		assert_eq!(
+			paths(&svg),
+			vec!["M0 0h0M2 1h0M4 4h0M6 4h0", "M40 40h0M42 40h0"]
+		);
+	}
+
+	#[test]
+	fn pipes() {
+		let svg = to_svg(&strokes(), &style(DrawingMode::Pipes), None);
+		assert_eq!(
+			paths(&svg),
+			vec!["M0 0L2 0L2 1L4 1L4 4L6 4", "M40 40L42 40"]
+		);
+	}
+
+	#[test]
+	fn pipe_routing() {
+		let style = DrawStyle {
+			pipe_routing: PipeRouting::FollowDirection,
+			..style(DrawingMode::Pipes)
+		};
+		let svg = to_svg(&strokes(), &style, None);
+		assert_eq!(paths(&svg), vec!["M0 0L2 0L2 4L6 4", "M40 40L42 40"]);
+		let style = DrawStyle {
+			pipe_routing: PipeRouting::Hashed,
+			..style
+		};
+		assert_eq!(
+			to_svg(&strokes(), &style, None),
+			to_svg(&strokes(), &style, None)
+		);
+	}
+
+	#[test]
+	fn curves() {
+		let svg = to_svg(&strokes(), &style(DrawingMode::Curve), None);
+		// The first and last point only steer the curve, and two points don't
+		// make a curve at all
+		assert_eq!(paths(&svg), vec!["M2 1C2.67 1.67 3.33 3.5 4 4"]);
+	}
+
+	#[test]
+	fn smoothing() {
+		let style = DrawStyle {
+			smoothing: Smoothing::Chaikin,
+			smoothing_strength: 1,
+			..style(DrawingMode::Lines)
+		};
+		let svg = to_svg(&strokes(), &style, None);
+		assert_eq!(
+			paths(&svg),
+			vec!["M0 0L1.5 0.75L2.5 1.75L3.5 3.25L4.5 4L6 4", "M40 40L42 40"]
+		);
+		// Dots stay where they are
+		let style = DrawStyle {
+			mode: DrawingMode::Points,
+			..style
+		};
+		assert_eq!(
+			to_svg(&strokes(), &style, None),
+			to_svg(
+				&strokes(),
+				&DrawStyle {
+					smoothing: Smoothing::None,
+					..style
+				},
+				None,
+			)
+		);
+	}
+
+	#[test]
+	fn centripetal_curves() {
+		let style = DrawStyle {
+			curve_parametrization: Parametrization::Centripetal,
+			..style(DrawingMode::Curve)
+		};
+		let svg = to_svg(&strokes(), &style, None);
+		assert_eq!(paths(&svg), vec!["M2 1C2.77 1.68 3.2 3.57 4 4"]);
+	}
+
+	#[test]
+	fn variable_width() {
+		let style = DrawStyle {
+			width_source: WidthSource::Taper,
+			stroke_opacity: 0.5,
+			..style(DrawingMode::Lines)
+		};
+		let svg = to_svg(&strokes(), &style, None);
+		assert!(svg.contains(
+			"<g fill=\"#000000\" fill-opacity=\"0.5\" fill-rule=\"nonzero\">"
+		));
+		// Every part is one closed outline
+		let paths = paths(&svg);
+		assert_eq!(paths.len(), 2);
+		for d in paths {
+			assert!(d.starts_with('M') && d.ends_with('Z'));
+			assert_eq!(d.matches('Z').count(), 1);
+		}
+	}
+
+	#[test]
+	fn numbers() {
+		assert_eq!(num(1.0), "1");
+		assert_eq!(num(-0.001), "0");
+		assert_eq!(num(1.005_1), "1.01");
+		assert_eq!(num(1.0 / 3.0), "0.33");
+	}
+}
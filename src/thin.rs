@@ -0,0 +1,190 @@
+use super::edge::EdgeMap;
+
+/// Thin out the edges to a width of one pixel with the Zhang-Suen algorithm.
+/// Pixels are peeled off the border of each edge in alternating passes from
+/// the south east and the north west, until only the skeleton is left, that
+/// keeps the edge connected.
+pub fn thin(edges: &mut EdgeMap) {
+	let mut to_remove: Vec<(usize, usize)> = Vec::new();
+	loop {
+		let mut changed = false;
+		for pass in 0..2 {
+			for y in 0..edges.height() {
+				for x in 0..edges.width() {
+					if edges.get(x, y) && is_removable(edges, x, y, pass) {
+						to_remove.push((x, y));
+					}
+				}
+			}
+			changed |= !to_remove.is_empty();
+			for (x, y) in to_remove.drain(..) {
+				edges.set(x, y, false);
+			}
+		}
+		if !changed {
+			break;
+		}
+	}
+}
+
+fn is_removable(edges: &EdgeMap, x: usize, y: usize, pass: usize) -> bool {
+	// The neighbours, clockwise, starting at the top
+	let n = neighbours(edges, x, y);
+	let count = n.iter().filter(|&&is_set| is_set).count();
+	if !(2..=6).contains(&count) {
+		return false;
+	}
+	// The number of transitions from background to edge around the pixel.
+	// Removing a pixel with more than one would split the edge.
+	let transitions = (0..8).filter(|&i| !n[i] && n[(i + 1) % 8]).count();
+	if transitions != 1 {
+		return false;
+	}
+	let [top, _, right, _, bottom, _, left, _] = n;
+	if pass == 0 {
+		!(right && bottom && (top || left))
+	} else {
+		!(top && left && (right || bottom))
+	}
+}
+
+fn neighbours(edges: &EdgeMap, x: usize, y: usize) -> [bool; 8] {
+	let get = |dx: isize, dy: isize| {
+		let nx = x as isize + dx;
+		let ny = y as isize + dy;
+		nx >= 0 &&
+			ny >= 0 &&
+			(nx as usize) < edges.width() &&
+			(ny as usize) < edges.height() &&
+			edges.get(nx as usize, ny as usize)
+	};
+	[
+		get(0, -1),
+		get(1, -1),
+		get(1, 0),
+		get(1, 1),
+		get(0, 1),
+		get(-1, 1),
+		get(-1, 0),
+		get(-1, -1),
+	]
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn edge_map(rows: &[&str]) -> EdgeMap {
+		let mut edges = EdgeMap::new(rows[0].len(), rows.len());
+		for (y, row) in rows.iter().enumerate() {
+			for (x, c) in row.chars().enumerate() {
+				edges.set(x, y, c == '#');
+			}
+		}
+		edges
+	}
+
+	fn count_neighbours(edges: &EdgeMap, x: usize, y: usize) -> usize {
+		neighbours(edges, x, y).iter().filter(|&&n| n).count()
+	}
+
+	#[test]
+	fn thick_horizontal_line() {
+		let mut edges = edge_map(&[
+			"..............",
+			".############.",
+			".############.",
+			".############.",
+			"..............",
+		]);
+		thin(&mut edges);
+		// Every column of the line keeps exactly one pixel, the ends get shorter
+		for x in 3..11 {
+			let column = (0..5).filter(|&y| edges.get(x, y)).count();
+			assert_eq!(column, 1, "column {}", x);
+		}
+		assert_connected_line(&edges);
+	}
+
+	/// Draw a line from (2, 2) to (x, y), with all the pixels closer than
+	/// radius to it
+	fn thick_line(x: f32, y: f32, radius: f32) -> EdgeMap {
+		let mut edges = EdgeMap::new(20, 20);
+		let (dx, dy) = (x - 2.0, y - 2.0);
+		let sq_len = dx * dx + dy * dy;
+		for py in 0..20 {
+			for px in 0..20 {
+				let (px, py) = (px as f32, py as f32);
+				let t = (((px - 2.0) * dx + (py - 2.0) * dy) / sq_len).clamp(0.0, 1.0);
+				let (ox, oy) = (2.0 + t * dx - px, 2.0 + t * dy - py);
+				edges.set(
+					px as usize,
+					py as usize,
+					ox * ox + oy * oy <= radius * radius,
+				);
+			}
+		}
+		edges
+	}
+
+	#[test]
+	fn thick_diagonal_lines() {
+		for (x, y) in [(15.0, 15.0), (17.0, 12.0), (8.0, 17.0)] {
+			let mut edges = thick_line(x, y, 1.5);
+			let before = edges.clone();
+			thin(&mut edges);
+			assert_connected_line(&edges);
+			// Thinning only removes pixels
+			for y in 0..20 {
+				for x in 0..20 {
+					assert!(before.get(x, y) || !edges.get(x, y));
+				}
+			}
+		}
+	}
+
+	/// Assert that the edge is a single line, that is one pixel wide: it is
+	/// connected, has exactly two ends and contains no 2x2 block of pixels
+	fn assert_connected_line(edges: &EdgeMap) {
+		let pixels: Vec<(usize, usize)> = (0..edges.height())
+			.flat_map(|y| (0..edges.width()).map(move |x| (x, y)))
+			.filter(|&(x, y)| edges.get(x, y))
+			.collect();
+		let ends = pixels
+			.iter()
+			.filter(|&&(x, y)| count_neighbours(edges, x, y) == 1)
+			.count();
+		assert_eq!(ends, 2);
+		for y in 1..edges.height() {
+			for x in 1..edges.width() {
+				let block = [(x - 1, y - 1), (x, y - 1), (x - 1, y), (x, y)];
+				assert!(
+					!block.iter().all(|&(x, y)| edges.get(x, y)),
+					"block at {}, {}",
+					x,
+					y
+				);
+			}
+		}
+		let mut visited = vec![pixels[0]];
+		let mut stack = vec![pixels[0]];
+		while let Some((x, y)) = stack.pop() {
+			for &(nx, ny) in &pixels {
+				let adjacent = nx.abs_diff(x) <= 1 && ny.abs_diff(y) <= 1;
+				if adjacent && !visited.contains(&(nx, ny)) {
+					visited.push((nx, ny));
+					stack.push((nx, ny));
+				}
+			}
+		}
+		assert_eq!(visited.len(), pixels.len());
+	}
+
+	#[test]
+	fn thin_lines_stay() {
+		let rows = ["......", ".####.", "....#.", "....#.", "......"];
+		let mut edges = edge_map(&rows);
+		thin(&mut edges);
+		assert_eq!(edges, edge_map(&rows));
+	}
+}
@@ -0,0 +1,478 @@
+use wasm_bindgen::prelude::*;
+
+use super::canny::canny_edges;
+use super::component::{
+	drop_small_components,
+	label_components,
+	sort_components,
+};
+use super::coord::{Coord, Indexed, Point};
+use super::dropout::{drop_out, drop_out_random, drop_out_spaced};
+use super::edge::{color_edges, quantized_edges, EdgeDetector, EdgeMap};
+use super::filter::FilterChain;
+use super::gradient::{gradient_edges, GradientKernel};
+use super::image::{GrayImage, Grayscale, RgbImage};
+use super::random::Random;
+use super::sort::sort_by_distance_2d;
+use super::stroke::Strokes;
+use super::thin::thin;
+
+const INITIAL_SEARCH_RADIUS: f32 = 2.0;
+
+/// Settings for turning an image into a list of sorted points
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TransformConfig {
+	/// The formula used to turn the colors of the image into gray values
+	pub grayscale: Grayscale,
+	pub edge_detector: EdgeDetector,
+	/// Bit depth the gray values are reduced to, when edges are detected by
+	/// quantization
+	pub edge_detection_bit_depth: u32,
+	/// Gradient magnitude, in gray values per pixel, a pixel needs to reach to
+	/// count as an edge. The Canny detector uses it as its high threshold, the
+	/// color distance detector as the minimum distance between two colors.
+	pub edge_threshold: f32,
+	/// Gradient magnitude weak edges need to reach, to be kept by the Canny
+	/// detector, when they are connected to a strong edge
+	pub edge_low_threshold: f32,
+	/// Standard deviation of the gaussian blur, that is applied before edges
+	/// are detected by the Canny detector
+	pub blur_sigma: f32,
+	/// Thin out the edges found by a gradient detector to a width of one pixel
+	pub non_max_suppression: bool,
+	/// Thin out the detected edges to a one pixel wide skeleton, before the
+	/// edge points are extracted
+	pub thin_edges: bool,
+	/// Groups of connected edge pixels, that are smaller than this, are
+	/// dropped as noise
+	pub min_component_size: u32,
+	/// Sort the points of each group of connected edge pixels on their own,
+	/// and chain the groups by proximity. Every group becomes a stroke.
+	pub sort_components: bool,
+	/// Share of the edge points that is kept, from 0 to 1
+	pub drop_out_percentage: f32,
+	/// Drop random points instead of every nth one
+	pub random_dropout: bool,
+	/// If above 0, points are dropped to keep at least this distance between
+	/// them, instead of dropping a percentage of them
+	pub min_point_distance: f32,
+	/// Seed of the random numbers used to drop points. The same seed always
+	/// drops the same points.
+	pub seed: u64,
+}
+
+#[wasm_bindgen]
+impl TransformConfig {
+	#[wasm_bindgen(constructor)]
+	pub fn new() -> TransformConfig {
+		Self {
+			grayscale: Grayscale::Average,
+			edge_detector: EdgeDetector::Quantize,
+			edge_detection_bit_depth: 3,
+			edge_threshold: 16.0,
+			edge_low_threshold: 6.0,
+			blur_sigma: 1.4,
+			non_max_suppression: true,
+			thin_edges: false,
+			min_component_size: 0,
+			sort_components: false,
+			drop_out_percentage: 1.0,
+			random_dropout: false,
+			min_point_distance: 0.0,
+			seed: 0,
+		}
+	}
+}
+
+impl Default for TransformConfig {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+pub fn detect_edges(img: &GrayImage, config: &TransformConfig) -> EdgeMap {
+	let mut edges = match config.edge_detector {
+		EdgeDetector::Quantize => {
+			quantized_edges(img, config.edge_detection_bit_depth)
+		}
+		EdgeDetector::Sobel => gradient_edges(
+			img,
+			GradientKernel::Sobel,
+			config.edge_threshold,
+			config.non_max_suppression,
+		),
+		EdgeDetector::Scharr => gradient_edges(
+			img,
+			GradientKernel::Scharr,
+			config.edge_threshold,
+			config.non_max_suppression,
+		),
+		EdgeDetector::Canny => canny_edges(
+			img,
+			config.blur_sigma,
+			config.edge_low_threshold,
+			config.edge_threshold,
+		),
+		// A gray image has no colors, so only its gray values are compared
+		EdgeDetector::ColorDistance => {
+			color_edges(&RgbImage::from(img), config.edge_threshold)
+		}
+	};
+	if config.thin_edges {
+		thin(&mut edges);
+	}
+	edges
+}
+
+/// Convert the image to gray values with the formula selected in `config`, run
+/// it through the filters and detect its edges. The color distance detector
+/// compares the colors of the unfiltered image instead.
+pub fn detect_rgb_edges(
+	img: &RgbImage,
+	config: &TransformConfig,
+	filters: &FilterChain,
+) -> EdgeMap {
+	if config.edge_detector == EdgeDetector::ColorDistance {
+		let mut edges = color_edges(img, config.edge_threshold);
+		if config.thin_edges {
+			thin(&mut edges);
+		}
+		return edges;
+	}
+	detect_edges(&filters.apply(img.to_gray(config.grayscale)), config)
+}
+
+/// Detect the edges of the image, drop some of the edge points, sort the rest
+/// and scale them from the size of the image to the target size. The points
+/// form a single stroke, unless the components are sorted on their own, in
+/// which case every component is a stroke.
+pub fn transform_image(
+	img: &RgbImage,
+	target_width: f32,
+	target_height: f32,
+	config: &TransformConfig,
+	filters: &FilterChain,
+) -> Strokes<Point> {
+	let (w, h) = (img.width() as f32, img.height() as f32);
+	let edges = detect_rgb_edges(img, config, filters);
+	let sorted = if config.min_component_size > 1 || config.sort_components {
+		let components = drop_small_components(
+			label_components(&edges),
+			config.min_component_size as usize,
+		);
+		// The points of all components are dropped at once, so that points of
+		// neighbouring components are spaced out, too
+		let points: Vec<Indexed<Point>> = components
+			.iter()
+			.enumerate()
+			.flat_map(|(index, c)| {
+				c.points()
+					.iter()
+					.map(move |&point| Indexed { point, index })
+			})
+			.collect();
+		let mut components = vec![Vec::new(); components.len()];
+		for p in drop_points(&points, config) {
+			components[p.index].push(p.point);
+		}
+		if config.sort_components {
+			sort_components(components, w, h, INITIAL_SEARCH_RADIUS)
+		} else {
+			// Back in row order, the sort starts at the same point as without
+			// dropping components
+			let mut points = components.concat();
+			points.sort_unstable_by(|a, b| {
+				(a.y(), a.x()).partial_cmp(&(b.y(), b.x())).unwrap()
+			});
+			Strokes::single(sort_by_distance_2d(points, w, h, INITIAL_SEARCH_RADIUS))
+		}
+	} else {
+		let points = drop_points(&edges.points(), config);
+		Strokes::single(sort_by_distance_2d(points, w, h, INITIAL_SEARCH_RADIUS))
+	};
+	let width_scale = target_width / img.width() as f32;
+	let height_scale = target_height / img.height() as f32;
+	let (points, offsets) = sorted.into_parts();
+	let points = points
+		.into_iter()
+		.map(|p| {
+			Point::new(
+				(p.x() * width_scale).floor(),
+				(p.y() * height_scale).floor(),
+			)
+		})
+		.collect();
+	Strokes::from_parts(points, &offsets)
+}
+
+/// Drop some of the points with the dropout selected in `config`
+fn drop_points<T: Coord>(points: &[T], config: &TransformConfig) -> Vec<T> {
+	let mut random = Random::new(config.seed);
+	if config.min_point_distance > 0.0 {
+		let seed = random.next_u64();
+		drop_out_spaced(points, config.min_point_distance, seed)
+	} else if config.random_dropout {
+		drop_out_random(points, config.drop_out_percentage, || random.next_f64())
+	} else {
+		drop_out(points, config.drop_out_percentage)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn transform_strokes(
+		img: &GrayImage,
+		target_width: f32,
+		target_height: f32,
+		config: &TransformConfig,
+	) -> Strokes<Point> {
+		let img = RgbImage::from(img);
+		transform_image(
+			&img,
+			target_width,
+			target_height,
+			config,
+			&FilterChain::new(),
+		)
+	}
+
+	fn transform(
+		img: &GrayImage,
+		target_width: f32,
+		target_height: f32,
+		config: &TransformConfig,
+	) -> Vec<Point> {
+		transform_strokes(img, target_width, target_height, config)
+			.into_parts()
+			.0
+	}
+
+	#[test]
+	fn square() {
+		// A bright 2x2 square in the middle of a dark 6x6 image
+		let img = GrayImage::from_fn(6, 6, |x, y| {
+			if (2..4).contains(&x) && (2..4).contains(&y) {
+				255.0
+			} else {
+				0.0
+			}
+		});
+		let points = transform(&img, 12.0, 12.0, &TransformConfig::new());
+		assert_eq!(
+			points,
+			vec![
+				Point::new(4.0, 4.0),
+				Point::new(6.0, 4.0),
+				Point::new(8.0, 4.0),
+				Point::new(8.0, 6.0),
+				Point::new(8.0, 8.0),
+				Point::new(6.0, 8.0),
+				Point::new(4.0, 8.0),
+				Point::new(4.0, 6.0),
+			]
+		);
+	}
+
+	#[test]
+	fn detectors() {
+		let img = GrayImage::from_fn(8, 8, |x, _| if x < 4 { 0.0 } else { 255.0 });
+		for edge_detector in [EdgeDetector::Sobel, EdgeDetector::Scharr] {
+			let config = TransformConfig {
+				edge_detector,
+				..TransformConfig::new()
+			};
+			let points = detect_edges(&img, &config).points();
+			assert_eq!(points.len(), 6);
+			assert!(points.iter().all(|p| p.x() == 3.0));
+		}
+		let config = TransformConfig {
+			edge_detector: EdgeDetector::Canny,
+			blur_sigma: 1.0,
+			..TransformConfig::new()
+		};
+		let points = detect_edges(&img, &config).points();
+		assert_eq!(points.len(), 6);
+		// The crest of the blurred step lies between two columns
+		let x = points[0].x();
+		assert!(x == 3.0 || x == 4.0);
+		assert!(points.iter().all(|p| p.x() == x));
+		let points = detect_edges(&img, &TransformConfig::new()).points();
+		assert_eq!(points.len(), 7);
+		assert!(points.iter().all(|p| p.x() == 4.0));
+	}
+
+	#[test]
+	fn dropout() {
+		let img = GrayImage::from_fn(8, 8, |x, _| (x * 32) as f32);
+		let config = TransformConfig {
+			drop_out_percentage: 0.5,
+			..TransformConfig::new()
+		};
+		let all = transform(&img, 8.0, 8.0, &TransformConfig::new());
+		let half = transform(&img, 8.0, 8.0, &config);
+		assert_eq!(half.len(), all.len() / 2);
+	}
+
+	#[test]
+	fn thin_edges() {
+		// A gradient ramp turns into a band of quantization edges
+		let img = GrayImage::from_fn(16, 8, |x, _| (x * 16) as f32);
+		let config = TransformConfig {
+			edge_detector: EdgeDetector::Sobel,
+			non_max_suppression: false,
+			edge_threshold: 1.0,
+			..TransformConfig::new()
+		};
+		let thick = detect_edges(&img, &config).points();
+		let config = TransformConfig {
+			thin_edges: true,
+			..config
+		};
+		let thin = detect_edges(&img, &config).points();
+		assert!(thin.len() < thick.len());
+		assert!(!thin.is_empty());
+	}
+
+	#[test]
+	fn components() {
+		// A square outline and a single speck
+		let img = GrayImage::from_fn(12, 12, |x, y| {
+			if (2..6).contains(&x) && (2..6).contains(&y) || x == 9 && y == 9 {
+				255.0
+			} else {
+				0.0
+			}
+		});
+		let all = transform(&img, 12.0, 12.0, &TransformConfig::new());
+		assert!(all.contains(&Point::new(9.0, 9.0)));
+		let config = TransformConfig {
+			min_component_size: 5,
+			sort_components: true,
+			..TransformConfig::new()
+		};
+		let points = transform(&img, 12.0, 12.0, &config);
+		assert!(points.len() < all.len());
+		assert!(points.iter().all(|p| p.x() < 8.0 && p.y() < 8.0));
+	}
+
+	#[test]
+	fn component_strokes() {
+		// Two square outlines, each of which becomes a stroke
+		let img = GrayImage::from_fn(16, 8, |x, y| {
+			let square = |left| (left..left + 4).contains(&x) && (2..6).contains(&y);
+			if square(2) || square(10) {
+				255.0
+			} else {
+				0.0
+			}
+		});
+		let config = TransformConfig {
+			sort_components: true,
+			..TransformConfig::new()
+		};
+		let strokes = transform_strokes(&img, 32.0, 16.0, &config);
+		assert_eq!(strokes.len(), 2);
+		for stroke in strokes.iter() {
+			let left = stroke.iter().all(|p| p.x() < 16.0);
+			let right = stroke.iter().all(|p| p.x() >= 16.0);
+			assert!(left != right, "{:?}", stroke);
+		}
+		// Without sorting the components, the points form a single stroke
+		let config = TransformConfig {
+			min_component_size: 2,
+			..TransformConfig::new()
+		};
+		let strokes = transform_strokes(&img, 32.0, 16.0, &config);
+		assert_eq!(strokes.len(), 1);
+		let all = transform(&img, 32.0, 16.0, &TransformConfig::new());
+		assert_eq!(strokes.points(), &all[..]);
+	}
+
+	#[test]
+	fn seeded_dropout() {
+		let img = GrayImage::from_fn(16, 16, |x, y| ((x + y) * 16) as f32);
+		let config = TransformConfig {
+			drop_out_percentage: 0.5,
+			random_dropout: true,
+			seed: 1234,
+			..TransformConfig::new()
+		};
+		let points = transform(&img, 16.0, 16.0, &config);
+		assert_eq!(points, transform(&img, 16.0, 16.0, &config));
+		let other_seed = TransformConfig { seed: 1, ..config };
+		assert_ne!(points, transform(&img, 16.0, 16.0, &other_seed));
+	}
+
+	#[test]
+	fn spaced_dropout() {
+		let img = GrayImage::from_fn(16, 16, |x, y| ((x + y) * 16) as f32);
+		let all = transform(&img, 16.0, 16.0, &TransformConfig::new());
+		let config = TransformConfig {
+			min_point_distance: 2.0,
+			..TransformConfig::new()
+		};
+		let points = transform(&img, 16.0, 16.0, &config);
+		assert!(points.len() < all.len());
+		for (i, a) in points.iter().enumerate() {
+			for b in &points[i + 1..] {
+				let (dx, dy) = (a.x() - b.x(), a.y() - b.y());
+				assert!(dx * dx + dy * dy >= 4.0);
+			}
+		}
+	}
+
+	#[test]
+	fn spaced_dropout_across_components() {
+		// Two bright stripes, whose edges are close, but not connected
+		let stripe = |x| x == 4 || x == 7;
+		let img =
+			GrayImage::from_fn(16, 16, |x, _| if stripe(x) { 255.0 } else { 0.0 });
+		let config = TransformConfig {
+			min_point_distance: 3.0,
+			min_component_size: 2,
+			sort_components: true,
+			..TransformConfig::new()
+		};
+		let strokes = transform_strokes(&img, 16.0, 16.0, &config);
+		assert!(strokes.len() > 1);
+		let points = strokes.points();
+		for (i, a) in points.iter().enumerate() {
+			for b in &points[i + 1..] {
+				let (dx, dy) = (a.x() - b.x(), a.y() - b.y());
+				assert!(dx * dx + dy * dy >= 9.0, "{:?} and {:?}", a, b);
+			}
+		}
+	}
+
+	#[test]
+	fn color_distance() {
+		// Red next to green of about the same brightness
+		let img = RgbImage::from_fn(8, 8, |x, _| {
+			if x < 4 {
+				[150.0, 0.0, 0.0]
+			} else {
+				[0.0, 150.0, 0.0]
+			}
+		});
+		let filters = FilterChain::new();
+		let gray = detect_rgb_edges(&img, &TransformConfig::new(), &filters);
+		assert!(gray.points().is_empty());
+		let config = TransformConfig {
+			edge_detector: EdgeDetector::ColorDistance,
+			..TransformConfig::new()
+		};
+		let points = detect_rgb_edges(&img, &config, &filters).points();
+		assert_eq!(points.len(), 7);
+		assert!(points.iter().all(|p| p.x() == 4.0));
+		// Comparing only the green channel reveals the edge as well
+		let config = TransformConfig {
+			grayscale: Grayscale::Green,
+			..TransformConfig::new()
+		};
+		let points = detect_rgb_edges(&img, &config, &filters).points();
+		assert_eq!(points.len(), 7);
+	}
+}
@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+
+use super::coord::{sq_dist, Coord, Indexed};
+use super::quadtree::{QuadTree, Rectangle};
+use super::stroke::Strokes;
+
+/// Number of following strokes each stroke is compared with by 2-opt. Limiting
+/// it keeps the passes linear in the number of strokes.
+const TWO_OPT_SPAN: usize = 64;
+const MAX_TWO_OPT_PASSES: usize = 8;
+
+/// The distance travelled with the pen up, before and after optimizing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TravelReport {
+	pub before: f64,
+	pub after: f64,
+}
+
+/// The total distance from the end of each stroke to the start of the next
+pub fn pen_up_distance<T: Coord>(strokes: &Strokes<T>) -> f64 {
+	strokes
+		.iter()
+		.zip(strokes.iter().skip(1))
+		.map(|(a, b)| dist(&a[a.len() - 1], &b[0]))
+		.sum()
+}
+
+/// Reorder and reverse the strokes, so that less distance is travelled with
+/// the pen up between them. The first stroke stays in place. The following
+/// ones are chained greedily, always continuing with the stroke that has the
+/// closest start or end, and the chain is then refined with 2-opt. If that
+/// doesn't shorten the travel, the strokes are returned unchanged.
+pub fn optimize_travel<T: Coord>(
+	strokes: &Strokes<T>,
+) -> (Strokes<T>, TravelReport) {
+	let before = pen_up_distance(strokes);
+	let mut order = greedy_order(strokes);
+	two_opt(strokes, &mut order);
+
+	let mut optimized = Strokes::new();
+	for &(i, reversed) in &order {
+		let stroke = strokes.get(i);
+		if reversed {
+			optimized.push(stroke.iter().rev().copied());
+		} else {
+			optimized.push(stroke.iter().copied());
+		}
+	}
+	let after = pen_up_distance(&optimized);
+	if after >= before {
+		let report = TravelReport {
+			before,
+			after: before,
+		};
+		return (strokes.clone(), report);
+	}
+	(optimized, TravelReport { before, after })
+}
+
+/// A stroke in the optimized order: its index, and whether it is reversed
+type Oriented = (usize, bool);
+
+fn start<T: Coord>(strokes: &Strokes<T>, (i, reversed): Oriented) -> T {
+	let stroke = strokes.get(i);
+	if reversed {
+		stroke[stroke.len() - 1]
+	} else {
+		stroke[0]
+	}
+}
+
+fn end<T: Coord>(strokes: &Strokes<T>, (i, reversed): Oriented) -> T {
+	start(strokes, (i, !reversed))
+}
+
+fn greedy_order<T: Coord>(strokes: &Strokes<T>) -> Vec<Oriented> {
+	if strokes.is_empty() {
+		return vec![];
+	}
+	// Endpoint `2 * i` is the start of stroke `i`, `2 * i + 1` its end. The
+	// tree only holds one point per position, so endpoints sharing a position
+	// are grouped.
+	let endpoints: Vec<T> = (0..strokes.len())
+		.flat_map(|i| [start(strokes, (i, false)), end(strokes, (i, false))])
+		.collect();
+	let mut qt = QuadTree::new(Rectangle::around(&endpoints));
+	let mut groups: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+	for (index, &point) in endpoints.iter().enumerate() {
+		groups.entry(position(&point)).or_default().push(index);
+		qt.insert(Indexed { point, index });
+	}
+	// Remove both endpoints of stroke `i`
+	let take = |i: usize,
+	            qt: &mut QuadTree<Indexed<T>>,
+	            groups: &mut HashMap<(u32, u32), Vec<usize>>| {
+		for index in [2 * i, 2 * i + 1] {
+			let point = endpoints[index];
+			let key = position(&point);
+			let group = groups.get_mut(&key).unwrap();
+			group.retain(|&other| other != index);
+			if group.is_empty() {
+				groups.remove(&key);
+				qt.remove(&Indexed { point, index });
+			}
+		}
+	};
+
+	let mut order = vec![(0, false)];
+	take(0, &mut qt, &mut groups);
+	let mut current = end(strokes, (0, false));
+	while let Some(nearest) = qt.nearest(current.x(), current.y()) {
+		let index = groups[&position(&nearest)][0];
+		let next = (index / 2, index % 2 == 1);
+		take(next.0, &mut qt, &mut groups);
+		order.push(next);
+		current = end(strokes, next);
+	}
+	order
+}
+
+/// Reverse runs of strokes, including their directions, whenever that
+/// shortens the travel to and from the run. The first stroke is never part of
+/// a run, so it stays in place.
+fn two_opt<T: Coord>(strokes: &Strokes<T>, order: &mut [Oriented]) {
+	let n = order.len();
+	for _ in 0..MAX_TWO_OPT_PASSES {
+		let mut improved = false;
+		for i in 1..n {
+			for j in i..n.min(i + TWO_OPT_SPAN) {
+				let first = start(strokes, order[i]);
+				let last = end(strokes, order[j]);
+				let before = end(strokes, order[i - 1]);
+				let mut old = dist(&before, &first);
+				let mut new = dist(&before, &last);
+				if j + 1 < n {
+					let after = start(strokes, order[j + 1]);
+					old += dist(&last, &after);
+					new += dist(&first, &after);
+				}
+				if new < old - 1e-9 {
+					order[i..=j].reverse();
+					for stroke in &mut order[i..=j] {
+						stroke.1 = !stroke.1;
+					}
+					improved = true;
+				}
+			}
+		}
+		if !improved {
+			break;
+		}
+	}
+}
+
+fn position<T: Coord>(p: &T) -> (u32, u32) {
+	// Adding 0 turns -0 into 0, which the tree considers the same position
+	((p.x() + 0.0).to_bits(), (p.y() + 0.0).to_bits())
+}
+
+fn dist<T: Coord>(a: &T, b: &T) -> f64 {
+	sq_dist(a, b).sqrt()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::coord::Point;
+	use crate::random::Random;
+
+	const P: fn(x: f32, y: f32) -> Point = Point::new;
+
+	fn strokes(list: &[&[(f32, f32)]]) -> Strokes<Point> {
+		let mut strokes = Strokes::new();
+		for stroke in list {
+			strokes.push(stroke.iter().map(|&(x, y)| P(x, y)));
+		}
+		strokes
+	}
+
+	/// The strokes, each in a canonical direction, sorted
+	fn canonical(strokes: &Strokes<Point>) -> Vec<Vec<(f32, f32)>> {
+		let mut list: Vec<Vec<(f32, f32)>> = strokes
+			.iter()
+			.map(|s| {
+				let forward: Vec<(f32, f32)> =
+					s.iter().map(|p| (p.x(), p.y())).collect();
+				let mut backward = forward.clone();
+				backward.reverse();
+				if forward.partial_cmp(&backward) == Some(std::cmp::Ordering::Greater) {
+					backward
+				} else {
+					forward
+				}
+			})
+			.collect();
+		list.sort_by(|a, b| a.partial_cmp(b).unwrap());
+		list
+	}
+
+	#[test]
+	fn reorders_and_reverses() {
+		let input = strokes(&[
+			&[(0.0, 0.0), (10.0, 0.0)],
+			&[(50.0, 0.0), (40.0, 0.0)],
+			&[(30.0, 0.0), (20.0, 0.0)],
+		]);
+		let (optimized, report) = optimize_travel(&input);
+		assert_eq!(
+			optimized,
+			strokes(&[
+				&[(0.0, 0.0), (10.0, 0.0)],
+				&[(20.0, 0.0), (30.0, 0.0)],
+				&[(40.0, 0.0), (50.0, 0.0)],
+			])
+		);
+		assert_eq!(report.before, 40.0 + 10.0);
+		assert_eq!(report.after, 20.0);
+		assert_eq!(pen_up_distance(&optimized), report.after);
+	}
+
+	#[test]
+	fn first_stroke_stays() {
+		// Reversing the first stroke would shorten the travel, but the plot
+		// has to start where it did
+		let input =
+			strokes(&[&[(10.0, 0.0), (0.0, 0.0)], &[(8.0, 0.0), (20.0, 0.0)]]);
+		let (optimized, report) = optimize_travel(&input);
+		assert_eq!(optimized, input);
+		assert_eq!(report.after, report.before);
+	}
+
+	#[test]
+	fn shared_endpoints() {
+		// Closed loops and strokes meeting in the same point
+		let input = strokes(&[
+			&[(0.0, 0.0), (5.0, 0.0), (0.0, 0.0)],
+			&[(20.0, 0.0), (10.0, 0.0)],
+			&[(10.0, 0.0), (10.0, 5.0), (10.0, 0.0)],
+			&[(5.0, 5.0)],
+		]);
+		let (optimized, report) = optimize_travel(&input);
+		assert_eq!(canonical(&optimized), canonical(&input));
+		assert!(report.after < report.before);
+	}
+
+	#[test]
+	fn never_worse() {
+		let mut random = Random::new(5);
+		let mut coordinate = || (random.next_f64() * 100.0).floor() as f32;
+		for size in [0, 1, 2, 5, 20, 200] {
+			let mut input = Strokes::new();
+			for _ in 0..size {
+				let length = 1 + (coordinate() as usize) % 4;
+				let stroke: Vec<Point> =
+					(0..length).map(|_| P(coordinate(), coordinate())).collect();
+				input.push(stroke);
+			}
+			let (optimized, report) = optimize_travel(&input);
+			assert_eq!(report.before, pen_up_distance(&input));
+			assert_eq!(report.after, pen_up_distance(&optimized));
+			assert!(report.after <= report.before);
+			assert_eq!(canonical(&optimized), canonical(&input));
+			if size > 0 {
+				assert_eq!(optimized.get(0), input.get(0));
+			}
+			if size >= 20 {
+				assert!(report.after < report.before * 0.5);
+			}
+		}
+	}
+}
@@ -0,0 +1,399 @@
+use std::f32::consts::PI;
+
+use wasm_bindgen::prelude::*;
+
+use super::coord::{Coord, Point};
+use super::gradient::{Gradient, GradientKernel};
+use super::image::GrayImage;
+use super::quadtree::{Circle, QuadTree, Rectangle};
+use super::stroke::Strokes;
+use super::style::DrawStyle;
+
+/// Radius of the area around a point, in which other points count towards its
+/// density
+const DENSITY_RADIUS: f32 = 4.0;
+/// Number of lines the round caps at the ends of outlines are made of
+const CAP_SEGMENTS: usize = 8;
+
+/// What the width of the lines depends on
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WidthSource {
+	/// All lines are `stroke_width` wide
+	Constant = 0,
+	/// Lines are wider where the edges of the image are stronger
+	EdgeStrength = 1,
+	/// Lines are thinner where many points are close together, so that
+	/// detailed areas don't turn into blots
+	Density = 2,
+	/// Lines get thinner towards the ends of the strokes, like brush strokes
+	Taper = 3,
+}
+
+/// How strong the edges of an image are, from 0 to 1, relative to the
+/// strongest one. It is looked up by the coordinates of a frame, that the
+/// image is stretched to.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrengthMap {
+	width: usize,
+	height: usize,
+	values: Vec<f32>,
+	/// Size of the frame the points are in
+	frame_width: f32,
+	frame_height: f32,
+}
+
+#[wasm_bindgen]
+impl StrengthMap {
+	/// The edge strengths of an RGBA image, like the `data` of an `ImageData`
+	/// object, whose points were scaled to `frame_width` x `frame_height`
+	#[wasm_bindgen(constructor)]
+	pub fn new(
+		rgba: &[u8],
+		width: u32,
+		height: u32,
+		frame_width: f32,
+		frame_height: f32,
+	) -> StrengthMap {
+		let img = GrayImage::from_rgba(rgba, width as usize, height as usize);
+		Self::from_image(&img, frame_width, frame_height)
+	}
+}
+
+impl StrengthMap {
+	/// The gradient magnitudes of the image, divided by the largest one
+	pub fn from_image(
+		img: &GrayImage,
+		frame_width: f32,
+		frame_height: f32,
+	) -> Self {
+		let mut values = Gradient::new(img, GradientKernel::Sobel).magnitudes();
+		let max = values.iter().copied().fold(0.0, f32::max);
+		if max > 0.0 {
+			values.iter_mut().for_each(|value| *value /= max);
+		}
+		Self {
+			width: img.width(),
+			height: img.height(),
+			values,
+			frame_width,
+			frame_height,
+		}
+	}
+
+	/// The strength of the pixel at a position in the frame
+	pub fn at<T: Coord>(&self, p: &T) -> f32 {
+		if self.values.is_empty() {
+			return 0.0;
+		}
+		let pixel = |value: f32, frame: f32, size: usize| {
+			let pixel = (value / frame * size as f32).floor().max(0.0) as usize;
+			pixel.min(size - 1)
+		};
+		let x = pixel(p.x(), self.frame_width, self.width);
+		let y = pixel(p.y(), self.frame_height, self.height);
+		self.values[x + y * self.width]
+	}
+}
+
+/// Decides the width of the lines at every point of a drawing
+pub struct StrokeWidths<'a> {
+	style: &'a DrawStyle,
+	strength: Option<&'a StrengthMap>,
+	/// All points of the drawing, for the density
+	points: Option<QuadTree<Point>>,
+}
+
+impl<'a> StrokeWidths<'a> {
+	/// Prepare the widths for the parts of a drawing. Without a strength map,
+	/// lines whose width depends on the edge strength are `stroke_width` wide.
+	pub fn new(
+		parts: &Strokes<Point>,
+		style: &'a DrawStyle,
+		strength: Option<&'a StrengthMap>,
+	) -> Self {
+		let points = (style.width_source == WidthSource::Density).then(|| {
+			let mut qt = QuadTree::new(Rectangle::around(parts.points()));
+			for &p in parts.points() {
+				qt.insert(p);
+			}
+			qt
+		});
+		Self {
+			style,
+			strength,
+			points,
+		}
+	}
+
+	/// The width at each point of a line
+	pub fn widths(&self, line: &[Point]) -> Vec<f32> {
+		let style = self.style;
+		let width = |weight: f32| {
+			let weight = weight.clamp(0.0, 1.0);
+			style.min_stroke_width +
+				(style.stroke_width - style.min_stroke_width) * weight
+		};
+		match style.width_source {
+			WidthSource::Constant => vec![style.stroke_width; line.len()],
+			WidthSource::EdgeStrength => match self.strength {
+				Some(strength) => line.iter().map(|p| width(strength.at(p))).collect(),
+				None => vec![style.stroke_width; line.len()],
+			},
+			WidthSource::Density => {
+				let qt = self.points.as_ref().unwrap();
+				// The share of the area around the point, that is filled with
+				// other points, assuming one point per square unit
+				let area = PI * DENSITY_RADIUS * DENSITY_RADIUS;
+				line
+					.iter()
+					.map(|p| {
+						let circle = Circle::new(p.x(), p.y(), DENSITY_RADIUS as f64);
+						let neighbours = qt.query(&circle).len().saturating_sub(1);
+						width(1.0 - neighbours as f32 / area)
+					})
+					.collect()
+			}
+			WidthSource::Taper => {
+				let mut lengths = Vec::with_capacity(line.len());
+				let mut length = 0.0;
+				for (i, p) in line.iter().enumerate() {
+					if i > 0 {
+						length += dist(&line[i - 1], p);
+					}
+					lengths.push(length);
+				}
+				let taper_length = style.taper_length.max(f32::EPSILON);
+				lengths
+					.iter()
+					.map(|l| width(l.min(length - l) / taper_length))
+					.collect()
+			}
+		}
+	}
+}
+
+/// The outline of a line with round caps, that is `widths[i]` wide at point
+/// `i`, as a polygon. A single point gives a circle. At sharp turns, the
+/// outline can cross itself, so it has to be filled with the nonzero rule.
+pub fn outline(line: &[Point], widths: &[f32]) -> Vec<Point> {
+	// Points in the same place have no direction
+	let mut points: Vec<(Point, f32)> = Vec::with_capacity(line.len());
+	for (&p, &width) in line.iter().zip(widths) {
+		match points.last_mut() {
+			Some((last, last_width)) if *last == p => {
+				*last_width = last_width.max(width)
+			}
+			_ => points.push((p, width)),
+		}
+	}
+	let Some(&(first, first_width)) = points.first() else {
+		return vec![];
+	};
+	if points.len() == 1 {
+		let radius = first_width / 2.0;
+		let mut circle = vec![Point::new(first.x() + radius, first.y())];
+		arc(&mut circle, &first, radius, 0.0, 2.0 * PI, CAP_SEGMENTS * 2);
+		return circle;
+	}
+
+	// The unit normals of the segments, pointing to the left
+	let normals: Vec<(f32, f32)> = points
+		.windows(2)
+		.map(|w| {
+			let (dx, dy) = (w[1].0.x() - w[0].0.x(), w[1].0.y() - w[0].0.y());
+			let length = dx.hypot(dy);
+			(-dy / length, dx / length)
+		})
+		.collect();
+	// Both sides of the line, from its start to its end. On the outside of a
+	// turn, the sides are joined by an arc. On the inside, they meet where
+	// the offset segments cross, but at most twice the width away from the
+	// line at sharp turns.
+	let mut left = Vec::with_capacity(points.len() + CAP_SEGMENTS);
+	let mut right = Vec::with_capacity(points.len() + CAP_SEGMENTS);
+	for (i, &(p, width)) in points.iter().enumerate() {
+		let radius = width / 2.0;
+		let before = normals[i.saturating_sub(1)];
+		let after = normals[i.min(normals.len() - 1)];
+		let offset = |(nx, ny): (f32, f32), scale: f32| {
+			Point::new(p.x() + nx * scale, p.y() + ny * scale)
+		};
+		if before == after {
+			left.push(offset(before, radius));
+			right.push(offset(before, -radius));
+			continue;
+		}
+		let (x, y) = (before.0 + after.0, before.1 + after.1);
+		let length = x.hypot(y);
+		let miter = if length > 1e-6 {
+			let miter = (x / length, y / length);
+			let scale = radius / (miter.0 * before.0 + miter.1 * before.1).max(0.5);
+			(miter.0 * scale, miter.1 * scale)
+		} else {
+			// The line turns back on itself
+			(0.0, 0.0)
+		};
+		// Whether the line turns towards its right side
+		let turns_right = before.0 * after.1 - before.1 * after.0 < 0.0;
+		let (outer, inner, sign) = if turns_right {
+			(&mut left, &mut right, 1.0)
+		} else {
+			(&mut right, &mut left, -1.0)
+		};
+		inner.push(offset(miter, -sign));
+		outer.push(offset(before, sign * radius));
+		let from = (sign * before.1).atan2(sign * before.0);
+		let mut to = (sign * after.1).atan2(sign * after.0);
+		if (to - from).abs() > PI {
+			to -= 2.0 * PI * (to - from).signum();
+		}
+		let segments =
+			((to - from).abs() / (PI / CAP_SEGMENTS as f32)).ceil() as usize;
+		arc(outer, &p, radius, from, to, segments);
+		outer.push(offset(after, sign * radius));
+	}
+
+	let mut polygon = left;
+	let (last, last_width) = points[points.len() - 1];
+	let (nx, ny) = normals[normals.len() - 1];
+	let angle = ny.atan2(nx);
+	arc(
+		&mut polygon,
+		&last,
+		last_width / 2.0,
+		angle,
+		angle - PI,
+		CAP_SEGMENTS,
+	);
+	polygon.extend(right.into_iter().rev());
+	let (nx, ny) = normals[0];
+	let angle = (-ny).atan2(-nx);
+	arc(
+		&mut polygon,
+		&first,
+		first_width / 2.0,
+		angle,
+		angle - PI,
+		CAP_SEGMENTS,
+	);
+	polygon
+}
+
+/// Add the points of an arc around `center`, leaving out the first and the
+/// last one
+fn arc(
+	polygon: &mut Vec<Point>,
+	center: &Point,
+	radius: f32,
+	from: f32,
+	to: f32,
+	segments: usize,
+) {
+	for i in 1..segments {
+		let angle = from + (to - from) * i as f32 / segments as f32;
+		polygon.push(Point::new(
+			center.x() + radius * angle.cos(),
+			center.y() + radius * angle.sin(),
+		));
+	}
+}
+
+fn dist(a: &Point, b: &Point) -> f32 {
+	(a.x() - b.x()).hypot(a.y() - b.y())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::simplify::segment_dist;
+	use crate::style::DrawingMode;
+
+	const P: fn(x: f32, y: f32) -> Point = Point::new;
+
+	fn style(width_source: WidthSource) -> DrawStyle {
+		DrawStyle {
+			mode: DrawingMode::Lines,
+			stroke_width: 4.0,
+			min_stroke_width: 1.0,
+			taper_length: 10.0,
+			width_source,
+			..DrawStyle::new(100.0, 100.0)
+		}
+	}
+
+	fn line() -> Vec<Point> {
+		(0..=40).map(|x| P(x as f32, 5.0)).collect()
+	}
+
+	#[test]
+	fn taper() {
+		let style = style(WidthSource::Taper);
+		let parts = Strokes::single(line());
+		let widths = StrokeWidths::new(&parts, &style, None).widths(&line());
+		assert_eq!(widths[0], 1.0);
+		assert_eq!(widths[5], 2.5);
+		assert_eq!(widths[10..=30], [4.0; 21]);
+		assert_eq!(widths[35], 2.5);
+		assert_eq!(widths[40], 1.0);
+	}
+
+	#[test]
+	fn density() {
+		let style = style(WidthSource::Density);
+		// A lone line next to a densely filled square
+		let mut parts = Strokes::single(line());
+		for y in 20..40 {
+			parts.push((0..20).map(|x| P(x as f32, y as f32)));
+		}
+		let widths = StrokeWidths::new(&parts, &style, None);
+		let [lone, filled] =
+			[P(20.0, 5.0), P(10.0, 30.0)].map(|p| widths.widths(&[p])[0]);
+		assert!(lone > 3.0);
+		assert!(filled < 1.5);
+	}
+
+	#[test]
+	fn edge_strength() {
+		// A vertical edge in the middle of a 10x10 image, which is stretched to
+		// a 20x20 frame
+		let img =
+			GrayImage::from_fn(10, 10, |x, _| if x < 5 { 0.0 } else { 255.0 });
+		let strength = StrengthMap::from_image(&img, 20.0, 20.0);
+		assert_eq!(strength.at(&P(9.0, 9.0)), 1.0);
+		assert_eq!(strength.at(&P(2.0, 9.0)), 0.0);
+		let style = style(WidthSource::EdgeStrength);
+		let parts = Strokes::single(line());
+		let widths = StrokeWidths::new(&parts, &style, Some(&strength));
+		assert_eq!(widths.widths(&[P(9.0, 9.0), P(2.0, 9.0)]), [4.0, 1.0]);
+		let widths = StrokeWidths::new(&parts, &style, None);
+		assert_eq!(widths.widths(&[P(9.0, 9.0), P(2.0, 9.0)]), [4.0, 4.0]);
+	}
+
+	#[test]
+	fn outlines() {
+		let line = [P(0.0, 0.0), P(10.0, 0.0), P(10.0, 0.0), P(10.0, 10.0)];
+		let widths = [2.0, 4.0, 4.0, 6.0];
+		let polygon = outline(&line, &widths);
+		// The outline keeps half the width away from the line, and is closed by
+		// round caps
+		for (i, p) in polygon.iter().enumerate() {
+			let distance = segment_dist(p, &line[0], &line[1])
+				.min(segment_dist(p, &line[2], &line[3]));
+			assert!(distance <= 3.0 + 1e-4, "{} {:?}", i, p);
+			assert!(distance >= 1.0 - 1e-4, "{} {:?}", i, p);
+		}
+		assert_eq!(polygon[0], P(0.0, 1.0));
+		assert!(polygon.contains(&P(0.0, -1.0)));
+		assert!(polygon.contains(&P(13.0, 10.0)));
+		assert!(polygon.contains(&P(7.0, 10.0)));
+
+		let dot = outline(&[P(5.0, 5.0)], &[2.0]);
+		assert_eq!(dot.len(), CAP_SEGMENTS * 2);
+		assert!(dot
+			.iter()
+			.all(|p| (dist(p, &P(5.0, 5.0)) - 1.0).abs() < 1e-5));
+		assert!(outline(&[], &[]).is_empty());
+	}
+}